@@ -0,0 +1,61 @@
+//! Times scanning one large log memory-mapped against reading it through a
+//! buffer, as `--mmap` does.
+//!
+//! Run with `cargo bench --bench mmap`. Set `RUSTWATCH_BENCH_MB` to change the
+//! size of the generated log (160 MiB by default); below `MMAP_THRESHOLD` it
+//! is read through a buffer either way.
+
+use std::env;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use rustwatch::{scan_directory, ScanOptions, MMAP_THRESHOLD};
+
+const RUNS: u32 = 5;
+
+fn generate_log(path: &Path, total_bytes: usize) {
+    let mut out = BufWriter::new(fs::File::create(path).unwrap());
+    let mut written = 0;
+    let mut line = 0;
+    while written < total_bytes {
+        // Few lines match, which is where mapping saves the most
+        let text = if line % 50 == 0 {
+            format!("2024-06-20 12:00:{:02} ERROR request {} failed: connection refused\n", line % 60, line)
+        } else {
+            format!("2024-06-20 12:00:{:02} INFO request {} served in {} ms\n", line % 60, line, line % 900)
+        };
+        out.write_all(text.as_bytes()).unwrap();
+        written += text.len();
+        line += 1;
+    }
+}
+
+fn bench(name: &str, dir: &Path, options: &ScanOptions) {
+    // One untimed run to warm the page cache
+    scan_directory(dir, options).unwrap();
+
+    let mut total = Duration::ZERO;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        scan_directory(dir, options).unwrap();
+        total += start.elapsed();
+    }
+    println!("{:<12} {:>8.1} ms", name, (total / RUNS).as_secs_f64() * 1000.0);
+}
+
+fn main() {
+    let megabytes: usize = env::var("RUSTWATCH_BENCH_MB").ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(160);
+    let dir = tempfile::tempdir().unwrap();
+    generate_log(&dir.path().join("app.log"), megabytes * 1024 * 1024);
+    if ((megabytes * 1024 * 1024) as u64) < MMAP_THRESHOLD {
+        eprintln!("The log is below the mmap threshold of {} MiB, so both runs read it through a buffer", MMAP_THRESHOLD / (1024 * 1024));
+    }
+
+    let buffered = ScanOptions::default();
+    let mapped = ScanOptions { use_mmap: true, ..ScanOptions::default() };
+    bench("buffered", dir.path(), &buffered);
+    bench("mmap", dir.path(), &mapped);
+}
@@ -0,0 +1,82 @@
+//! Times full scans of generated logs and reports their throughput.
+//!
+//! Run with `cargo bench --bench scan`. Set `RUSTWATCH_BENCH_MB` to change the
+//! amount of log data generated (160 MiB by default), split over two files.
+//! Memory mapping is compared in the `mmap` bench.
+//!
+//! Many tiny files are scanned too, once with read buffers sized to each file
+//! and once with the fixed `READ_BUFFER_SIZE` that `--buffer-size 128K` gives.
+
+use std::env;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use rustwatch::{scan_directory, ScanOptions, READ_BUFFER_SIZE};
+
+const FILES: usize = 2;
+const TINY_FILES: usize = 20_000;
+const RUNS: u32 = 5;
+
+fn generate_logs(dir: &Path, total_bytes: usize) {
+    for file in 0..FILES {
+        let mut out = BufWriter::new(fs::File::create(dir.join(format!("app{}.log", file))).unwrap());
+        let mut written = 0;
+        let mut line = 0;
+        while written < total_bytes / FILES {
+            let text = if line % 50 == 0 {
+                format!("2024-06-20 12:00:{:02} ERROR request {} failed: connection refused\n", line % 60, line)
+            } else {
+                format!("2024-06-20 12:00:{:02} INFO request {} served in {} ms\n", line % 60, line, line % 900)
+            };
+            out.write_all(text.as_bytes()).unwrap();
+            written += text.len();
+            line += 1;
+        }
+    }
+}
+
+fn generate_tiny_logs(dir: &Path) {
+    for file in 0..TINY_FILES {
+        let text = format!("2024-06-20 12:00:{:02} INFO worker {} started\n2024-06-20 12:00:{:02} ERROR worker {} exited\n",
+            file % 60, file, file % 60, file);
+        fs::write(dir.join(format!("worker{}.log", file)), text).unwrap();
+    }
+}
+
+fn bench(name: &str, dir: &Path, options: &ScanOptions) {
+    // One untimed run to warm the page cache
+    scan_directory(dir, options).unwrap();
+
+    let mut total = Duration::ZERO;
+    let mut report = None;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        report = Some(scan_directory(dir, options).unwrap());
+        total += start.elapsed();
+    }
+    let stats = report.unwrap().stats;
+    let average = total / RUNS;
+    let mib_per_second = stats.bytes_read as f64 / (1024.0 * 1024.0) / average.as_secs_f64();
+    println!("{:<12} {:>8.1} ms {:>10.1} MiB/s {:>12.0} lines/s",
+        name,
+        average.as_secs_f64() * 1000.0,
+        mib_per_second,
+        stats.lines_read as f64 / average.as_secs_f64());
+}
+
+fn main() {
+    let megabytes: usize = env::var("RUSTWATCH_BENCH_MB").ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(160);
+    let dir = tempfile::tempdir().unwrap();
+    generate_logs(dir.path(), megabytes * 1024 * 1024);
+
+    bench("scan", dir.path(), &ScanOptions::default());
+
+    let tiny_dir = tempfile::tempdir().unwrap();
+    generate_tiny_logs(tiny_dir.path());
+    let fixed = ScanOptions { buffer_size: Some(READ_BUFFER_SIZE), ..ScanOptions::default() };
+    bench("tiny auto", tiny_dir.path(), &ScanOptions::default());
+    bench("tiny 128K", tiny_dir.path(), &fixed);
+}
@@ -0,0 +1,63 @@
+use rustwatch::Severity;
+
+// The level is looked for among this many leading tokens, which covers
+// layouts like "2024-06-20 12:00:00,123 [worker-3] ERROR"
+const MAX_PREFIX_TOKENS: usize = 6;
+
+// Levels in a column are in capitals (ERROR) or set off in brackets
+// ([error]), which keeps a lowercase "error" early in a message from counting
+fn is_level(token: &str) -> bool {
+    let name = token.trim_matches(|c: char| !c.is_ascii_alphabetic());
+    let bracketed = token.starts_with(['[', '<', '(']);
+    (bracketed || name.bytes().all(|b| b.is_ascii_uppercase())) && name.parse::<Severity>().is_ok()
+}
+
+// Splits a line into what comes before its level (usually a timestamp), the
+// level itself and the message after it. `None` if no level shows up early
+// enough.
+fn split_at_level(line: &str) -> Option<(&str, &str, &str)> {
+    let line = line.trim_start();
+    let mut start = 0;
+    for _ in 0..MAX_PREFIX_TOKENS {
+        let rest = &line[start..];
+        let token_start = start + (rest.len() - rest.trim_start().len());
+        let token_end = line[token_start..].find(char::is_whitespace).map_or(line.len(), |end| token_start + end);
+        if token_start == token_end {
+            break;
+        }
+        if is_level(&line[token_start..token_end]) {
+            return Some((line[..token_start].trim_end(), &line[token_start..token_end], line[token_end..].trim_start()));
+        }
+        start = token_end;
+    }
+    None
+}
+
+/// Lines up the lead (timestamp, thread...), level and message columns of a
+/// file's matched lines, for `--align`.
+///
+/// Lines without a recognizable level near the start are returned
+/// unchanged. Returns `None` when fewer than two lines have one, as there is
+/// nothing to align then.
+pub fn align_columns(lines: &[&str]) -> Option<Vec<String>> {
+    let parts: Vec<_> = lines.iter().map(|line| split_at_level(line)).collect();
+    if parts.iter().flatten().count() < 2 {
+        return None;
+    }
+
+    let (lead_width, level_width) = parts.iter().flatten()
+        .fold((0, 0), |(leads, levels), (lead, level, _)| (
+            leads.max(lead.chars().count()),
+            levels.max(level.chars().count()),
+        ));
+
+    Some(lines.iter().zip(parts).map(|(line, parts)| match parts {
+        Some((lead, level, message)) => format!("{:<lead_width$} {:<level_width$} {}",
+            lead,
+            level,
+            message,
+            lead_width = lead_width,
+            level_width = level_width),
+        None => line.to_string(),
+    }).collect())
+}
@@ -0,0 +1,109 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use chrono::{Local, NaiveDate, TimeZone};
+use flate2::read::GzDecoder;
+
+use crate::error::{AppError, Result};
+use crate::scanner::{process_member, FileReport, ScanOptions};
+
+// Each member's report, or `None` for members that aren't text
+pub(crate) type MemberResults = Vec<(PathBuf, Result<Option<FileReport>>)>;
+
+enum ArchiveKind {
+    Tar,
+    TarGz,
+    Zip,
+}
+
+fn archive_kind(path: &Path) -> Option<ArchiveKind> {
+    let name = path.file_name()?.to_str()?.to_lowercase();
+    if name.ends_with(".tar") {
+        Some(ArchiveKind::Tar)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(ArchiveKind::TarGz)
+    } else if name.ends_with(".zip") {
+        Some(ArchiveKind::Zip)
+    } else {
+        None
+    }
+}
+
+/// Whether `path` is an archive (`.tar`, `.tar.gz`, `.tgz` or `.zip`) whose
+/// members are scanned as files of their own.
+pub fn is_archive(path: &Path) -> bool {
+    archive_kind(path).is_some()
+}
+
+/// The path a member is reported under, e.g. `bundle.tar.gz::app.log`.
+pub fn member_path(archive: &Path, member: &str) -> PathBuf {
+    PathBuf::from(format!("{}::{}", archive.display(), member))
+}
+
+fn archive_error(path: &Path, error: impl ToString) -> AppError {
+    AppError::FileProcessingError {
+        path: path.to_path_buf(),
+        error: error.to_string(),
+    }
+}
+
+/// Scans every file in an archive, in the order they are stored.
+///
+/// A member that can't be read only fails on its own, but a damaged archive
+/// fails as a whole.
+pub(crate) fn scan_archive(path: &Path, options: &ScanOptions) -> Result<MemberResults> {
+    let file = File::open(path).map_err(|e| archive_error(path, e))?;
+    match archive_kind(path) {
+        Some(ArchiveKind::Tar) => scan_tar(file, path, options),
+        Some(ArchiveKind::TarGz) => scan_tar(GzDecoder::new(file), path, options),
+        Some(ArchiveKind::Zip) => scan_zip(file, path, options),
+        None => Err(archive_error(path, "not a tar or zip archive")),
+    }
+}
+
+fn scan_tar(source: impl Read, path: &Path, options: &ScanOptions) -> Result<MemberResults> {
+    let mut archive = tar::Archive::new(source);
+    let mut members = Vec::new();
+    // Members can only be read in order, one after the other
+    for entry in archive.entries().map_err(|e| archive_error(path, e))? {
+        let entry = entry.map_err(|e| archive_error(path, e))?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let entry_path = entry.path().map_err(|e| archive_error(path, e))?.display().to_string();
+        // Archives made with `tar -C dir .` prefix every member with ./
+        let name = entry_path.trim_start_matches("./").to_string();
+        let size = entry.size();
+        let modified = entry.header().mtime().ok().map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+        let member = member_path(path, &name);
+        let result = process_member(entry, &name, size, modified, &member, options);
+        members.push((member, result));
+    }
+    Ok(members)
+}
+
+fn scan_zip(file: File, path: &Path, options: &ScanOptions) -> Result<MemberResults> {
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| archive_error(path, e))?;
+    let mut members = Vec::new();
+    for index in 0..archive.len() {
+        let entry = archive.by_index(index).map_err(|e| archive_error(path, e))?;
+        if !entry.is_file() {
+            continue;
+        }
+        let name = entry.name().to_string();
+        let size = entry.size();
+        let modified = entry.last_modified().and_then(zip_time);
+        let member = member_path(path, &name);
+        let result = process_member(entry, &name, size, modified, &member, options);
+        members.push((member, result));
+    }
+    Ok(members)
+}
+
+// Zip timestamps are in local time, without a zone
+fn zip_time(datetime: zip::DateTime) -> Option<SystemTime> {
+    let naive = NaiveDate::from_ymd_opt(datetime.year().into(), datetime.month().into(), datetime.day().into())?
+        .and_hms_opt(datetime.hour().into(), datetime.minute().into(), datetime.second().into())?;
+    Local.from_local_datetime(&naive).earliest().map(SystemTime::from)
+}
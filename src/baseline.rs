@@ -0,0 +1,56 @@
+use std::fs;
+use std::path::Path;
+
+use crate::error::{AppError, Result};
+
+/// Known, already triaged messages whose matches should not be reported.
+///
+/// Each pattern suppresses the lines that contain it. A `*` in a pattern
+/// stands for any run of characters, so `connection to * refused` covers
+/// every host.
+#[derive(Clone, Debug, Default)]
+pub struct Baseline {
+    // Each pattern split at its wildcards
+    patterns: Vec<Vec<String>>,
+}
+
+impl Baseline {
+    pub fn new<I, S>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let patterns = patterns.into_iter()
+            .filter(|pattern| !pattern.as_ref().is_empty())
+            .map(|pattern| pattern.as_ref().split('*').map(str::to_string).collect())
+            .collect();
+        Self { patterns }
+    }
+
+    /// Reads one pattern per line, skipping blank lines and `#` comments.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).map_err(|e| AppError::InvalidInput(
+            format!("Cannot read baseline file {}: {}", path.display(), e)
+        ))?;
+        Ok(Self::new(text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))))
+    }
+
+    /// Whether `line` matches any of the patterns.
+    pub fn suppresses(&self, line: &str) -> bool {
+        self.patterns.iter().any(|parts| contains_in_order(line, parts))
+    }
+}
+
+// Whether the parts occur in `line` one after another, without overlapping
+fn contains_in_order(line: &str, parts: &[String]) -> bool {
+    let mut rest = line;
+    for part in parts {
+        match rest.find(part.as_str()) {
+            Some(start) => rest = &rest[start + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
@@ -0,0 +1,82 @@
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+/// A byte sequence searched for in the raw bytes of each line, written in
+/// hex such as `0xDEADBEEF` or `de ad be ef`.
+///
+/// Lines are matched as rendered by [`escape_bytes`], so this works on lines
+/// that aren't valid UTF-8 and on control characters alike.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BytePattern {
+    bytes: Vec<u8>,
+    // The bytes as escape_bytes renders them, which is what lines are searched for
+    escaped: String,
+}
+
+impl FromStr for BytePattern {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let trimmed = value.trim();
+        let hex: String = trimmed.strip_prefix("0x").or_else(|| trimmed.strip_prefix("0X")).unwrap_or(trimmed)
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != ':')
+            .collect();
+        if hex.is_empty() || !hex.len().is_multiple_of(2) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("invalid byte pattern '{}' (expected pairs of hex digits, e.g. 0xDEADBEEF)", value));
+        }
+
+        let bytes: Vec<u8> = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("checked to be hex digits"))
+            .collect();
+        let escaped = escape_bytes(&bytes);
+        Ok(Self { bytes, escaped })
+    }
+}
+
+impl fmt::Display for BytePattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x")?;
+        for byte in &self.bytes {
+            write!(f, "{:02X}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl BytePattern {
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Byte ranges of the pattern's occurrences in a line rendered by
+    /// [`escape_bytes`].
+    pub fn find_in<'l>(&'l self, line: &'l str) -> impl Iterator<Item = Range<usize>> + 'l {
+        memchr::memmem::find_iter(line.as_bytes(), self.escaped.as_bytes())
+            // A backslash always starts a four-character escape, so a match
+            // can't begin in the middle of one
+            .filter(move |&start| !line.as_bytes()[start.saturating_sub(3)..start].contains(&b'\\'))
+            .map(|start| start..start + self.escaped.len())
+    }
+
+    /// Whether the pattern occurs in a line rendered by [`escape_bytes`].
+    pub fn is_found_in(&self, line: &str) -> bool {
+        self.find_in(line).next().is_some()
+    }
+}
+
+/// Renders raw bytes as text that is safe to print: printable ASCII and tabs
+/// stay as they are, every other byte (backslashes included) becomes `\xNN`.
+pub fn escape_bytes(bytes: &[u8]) -> String {
+    let mut escaped = String::with_capacity(bytes.len());
+    for &byte in bytes {
+        if matches!(byte, b'\t' | b' '..=b'~') && byte != b'\\' {
+            escaped.push(char::from(byte));
+        } else {
+            escaped.push_str(&format!("\\x{:02X}", byte));
+        }
+    }
+    escaped
+}
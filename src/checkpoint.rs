@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, Result};
+use crate::scanner::FileReport;
+use crate::state::state_key;
+use crate::theme::Themed;
+use crate::FileOutcome;
+
+// How often the checkpoint is written while files keep finishing
+const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+// A finished file's outcome, without the failures, which are retried
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SavedOutcome {
+    Scanned(FileReport),
+    NotText,
+    Archive(Vec<(PathBuf, SavedOutcome)>),
+}
+
+impl SavedOutcome {
+    // `None` for outcomes worth another try: failures and unscanned files
+    fn from_outcome(outcome: &FileOutcome) -> Option<Self> {
+        match outcome {
+            FileOutcome::Scanned(Ok(report)) => Some(SavedOutcome::Scanned(report.clone())),
+            FileOutcome::NotText => Some(SavedOutcome::NotText),
+            FileOutcome::Archive(members) => members.iter()
+                .map(|(member_path, outcome)| Self::from_outcome(outcome).map(|saved| (member_path.clone(), saved)))
+                .collect::<Option<_>>()
+                .map(SavedOutcome::Archive),
+            FileOutcome::Scanned(Err(_)) | FileOutcome::Unscanned => None,
+        }
+    }
+
+    fn into_outcome(self) -> FileOutcome {
+        match self {
+            SavedOutcome::Scanned(report) => FileOutcome::Scanned(Ok(report)),
+            SavedOutcome::NotText => FileOutcome::NotText,
+            SavedOutcome::Archive(members) => FileOutcome::Archive(members.into_iter()
+                .map(|(member_path, saved)| (member_path, saved.into_outcome()))
+                .collect()),
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct CheckpointFile {
+    files: HashMap<PathBuf, SavedOutcome>,
+}
+
+/// The files a scan has finished, written to disk as it goes so that an
+/// interrupted scan can be picked up again (see
+/// [`ScanOptions::checkpoint`](crate::ScanOptions::checkpoint)).
+///
+/// Finished files are not scanned again; their saved results are reported
+/// along with those of the files scanned this time. Files that failed are
+/// left out, so they are retried.
+#[derive(Debug)]
+pub struct Checkpoint {
+    path: PathBuf,
+    files: Mutex<HashMap<PathBuf, SavedOutcome>>,
+    last_saved: Mutex<Instant>,
+}
+
+impl Checkpoint {
+    /// Reads the checkpoint at `path`, which is also where it is saved. A
+    /// missing file is an empty checkpoint.
+    pub fn load(path: &Path) -> Result<Self> {
+        let checkpoint = match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).map_err(|e| AppError::InvalidInput(
+                format!("Invalid resume file {}: {}", path.display(), e)
+            ))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => CheckpointFile::default(),
+            Err(e) => return Err(AppError::IoError(e)),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            files: Mutex::new(checkpoint.files),
+            last_saved: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// Whether `path` is recorded as finished.
+    pub fn contains(&self, path: &Path) -> bool {
+        self.files.lock().unwrap().contains_key(&state_key(path))
+    }
+
+    /// Writes the checkpoint, replacing the previous one in one step.
+    pub fn save(&self) -> Result<()> {
+        let mut last_saved = self.last_saved.lock().unwrap();
+        self.write()?;
+        *last_saved = Instant::now();
+        Ok(())
+    }
+
+    // Only called with `last_saved` locked, so saves don't overlap
+    fn write(&self) -> Result<()> {
+        let checkpoint = CheckpointFile { files: self.files.lock().unwrap().clone() };
+        let json = serde_json::to_string(&checkpoint).map_err(io::Error::from)?;
+
+        // Write to a temporary file first so an interrupted save can't leave
+        // a truncated checkpoint behind
+        let temp_path = self.path.with_extension("tmp");
+        fs::write(&temp_path, json)?;
+        fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+
+    // The saved outcome of a file finished by an earlier run
+    pub(crate) fn finished(&self, path: &Path) -> Option<FileOutcome> {
+        self.files.lock().unwrap().get(&state_key(path)).cloned().map(SavedOutcome::into_outcome)
+    }
+
+    // Records a file as finished, saving the checkpoint if the last save was
+    // a while ago
+    pub(crate) fn record(&self, path: &Path, outcome: &FileOutcome) {
+        let Some(saved) = SavedOutcome::from_outcome(outcome) else {
+            return;
+        };
+        self.files.lock().unwrap().insert(state_key(path), saved);
+
+        // Another thread already saving covers this file too, or the next save will
+        let Ok(mut last_saved) = self.last_saved.try_lock() else {
+            return;
+        };
+        if last_saved.elapsed() >= SAVE_INTERVAL {
+            if let Err(e) = self.write() {
+                eprintln!("{} Cannot save resume file {}: {}", "⚠️".warn(), self.path.display(), e.to_string().warn());
+            }
+            *last_saved = Instant::now();
+        }
+    }
+}
@@ -0,0 +1,638 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read, Seek};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use colored::*;
+use flate2::read::GzDecoder;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+
+use crate::archive::is_archive;
+use crate::error::{AppError, Result};
+use crate::journal::JOURNAL_PATH;
+use crate::scanner::{is_gzip_file, ScanOptions};
+use crate::theme::Themed;
+
+const TEXT_FILE_EXTENSIONS: &[&str] = &[
+    "log", "txt", "text", "err", "out", "output", "debug",
+    "conf", "config", "cfg", "ini", "properties",
+    "yml", "yaml", "json", "xml", "env",
+    "md", "rst", "info"
+];
+
+// `extra_extensions` are expected in lowercase, without the leading dot
+fn has_text_extension(path: &Path, extra_extensions: &[String]) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase)
+        .is_some_and(|ext| TEXT_FILE_EXTENSIONS.contains(&ext.as_str()) || extra_extensions.contains(&ext))
+}
+
+/// Default number of bytes sniffed to tell text from binary.
+pub const SNIFF_LEN: usize = 512;
+/// Default share of null bytes at which sniffed content counts as binary.
+pub const MAX_NULL_RATIO: f32 = 0.01;
+/// Default share of non-ASCII bytes at which sniffed content counts as binary.
+pub const MAX_NON_ASCII_RATIO: f32 = 0.3;
+
+/// How the start of a file is judged to be text or binary.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SniffSettings {
+    /// How many bytes from the start are looked at
+    pub bytes: usize,
+    /// Content with at least this share of null bytes is binary
+    pub max_null_ratio: f32,
+    /// Content with at least this share of bytes outside ASCII is binary
+    pub max_non_ascii_ratio: f32,
+}
+
+impl Default for SniffSettings {
+    fn default() -> Self {
+        Self {
+            bytes: SNIFF_LEN,
+            max_null_ratio: MAX_NULL_RATIO,
+            max_non_ascii_ratio: MAX_NON_ASCII_RATIO,
+        }
+    }
+}
+
+impl SniffSettings {
+    pub(crate) fn looks_like_text(&self, reader: impl Read) -> bool {
+        let mut buffer = Vec::new();
+        if reader.take(self.bytes as u64).read_to_end(&mut buffer).is_err() || buffer.is_empty() {
+            return false;  // Unreadable or empty file
+        }
+
+        // UTF-16 text is full of null bytes, but its byte order mark gives it away
+        if buffer.starts_with(&[0xFF, 0xFE]) || buffer.starts_with(&[0xFE, 0xFF]) {
+            return true;
+        }
+
+        // Check for null bytes and high concentration of non-ASCII chars
+        let size = buffer.len() as f32;
+        let null_bytes = buffer.iter().filter(|&&b| b == 0).count();
+        let non_ascii = buffer.iter().filter(|&&b| b > 127).count();
+        (null_bytes as f32 / size) < self.max_null_ratio
+            && (non_ascii as f32 / size) < self.max_non_ascii_ratio
+    }
+}
+
+/// Decides whether a file is worth scanning as text.
+///
+/// Known log and config extensions are accepted immediately; anything else
+/// is judged by sniffing the first 512 bytes: more than 1% null bytes or 30%
+/// non-ASCII bytes make it binary. Gzip files are judged by the name and
+/// content of what's inside.
+pub fn is_text_file(path: &Path) -> bool {
+    is_text_file_with(path, &[], &SniffSettings::default())
+}
+
+/// Like [`is_text_file`], but sniffs the content with other settings.
+pub fn is_text_file_sniffing(path: &Path, sniff: &SniffSettings) -> bool {
+    is_text_file_with(path, &[], sniff)
+}
+
+// --verbose output, kept on stderr so it doesn't mix with the results
+pub(crate) fn log_skipped(options: &ScanOptions, path: &Path, reason: &str) {
+    if options.verbosity >= 1 {
+        eprintln!("{} Skipped {}: {}", "⏭️".dimmed(), path.display(), reason.dimmed());
+    }
+}
+
+fn log_entered(options: &ScanOptions, dir: &Path) {
+    if options.verbosity >= 2 {
+        eprintln!("{} Entering {}", "📂".dimmed(), dir.display());
+    }
+}
+
+pub(crate) fn is_text_file_with(path: &Path, extra_extensions: &[String], sniff: &SniffSettings) -> bool {
+    // Check extension first
+    if has_known_extension(path, extra_extensions) {
+        return true;
+    }
+
+    // If no extension or not in list, try to read first few bytes
+    fs::File::open(path)
+        .and_then(|file| open_file_is_text(&file, path, sniff))
+        .unwrap_or(false)
+}
+
+// Gzip files go by the extension of what's inside
+pub(crate) fn has_known_extension(path: &Path, extra_extensions: &[String]) -> bool {
+    if is_gzip_file(path) {
+        has_text_extension(&path.with_extension(""), extra_extensions)
+    } else {
+        has_text_extension(path, extra_extensions)
+    }
+}
+
+/// Sniffs a file that is already open, then rewinds it so it can be scanned
+/// without opening it again.
+pub(crate) fn open_file_is_text(mut file: &fs::File, path: &Path, sniff: &SniffSettings) -> io::Result<bool> {
+    let is_text = if is_gzip_file(path) {
+        sniff.looks_like_text(GzDecoder::new(file))
+    } else {
+        sniff.looks_like_text(file)
+    };
+    file.rewind()?;
+    Ok(is_text)
+}
+
+/// Compiles `--exclude` style glob patterns, returning `None` when there are none.
+pub fn build_exclude_set(patterns: &[String]) -> Result<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|e| AppError::InvalidInput(
+            format!("Invalid exclude pattern '{}': {}", pattern, e)
+        ))?;
+        builder.add(glob);
+    }
+    builder.build()
+        .map(Some)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid exclude patterns: {}", e)))
+}
+
+// Patterns are matched against the path relative to the scan root as well
+// as the full path. Directories also get a trailing slash so that a
+// pattern like 'journal/*' excludes the directory itself.
+fn is_excluded(options: &ScanOptions, root: &Path, path: &Path, is_dir: bool) -> bool {
+    let Some(excludes) = &options.excludes else {
+        return false;
+    };
+
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let matches = |candidate: &Path| {
+        excludes.is_match(candidate)
+            || (is_dir && excludes.is_match(format!("{}/", candidate.display())))
+    };
+    matches(relative) || matches(path)
+}
+
+// Splits off the leading run of digits or non-digits
+fn next_chunk(s: &str) -> (&str, &str) {
+    let is_digit = s.starts_with(|c: char| c.is_ascii_digit());
+    let end = s.find(|c: char| c.is_ascii_digit() != is_digit).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+/// Compares strings in natural order, so embedded numbers compare by value
+/// (`app.log.2` sorts before `app.log.10`).
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_rest, mut b_rest) = (a, b);
+
+    while !a_rest.is_empty() && !b_rest.is_empty() {
+        let (a_chunk, a_tail) = next_chunk(a_rest);
+        let (b_chunk, b_tail) = next_chunk(b_rest);
+
+        let both_numeric = a_chunk.starts_with(|c: char| c.is_ascii_digit())
+            && b_chunk.starts_with(|c: char| c.is_ascii_digit());
+        let ordering = if both_numeric {
+            // Compare by value without parsing, so arbitrarily long numbers work
+            let a_digits = a_chunk.trim_start_matches('0');
+            let b_digits = b_chunk.trim_start_matches('0');
+            a_digits.len().cmp(&b_digits.len()).then_with(|| a_digits.cmp(b_digits))
+        } else {
+            a_chunk.cmp(b_chunk)
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+        a_rest = a_tail;
+        b_rest = b_tail;
+    }
+
+    // Equal up to here: the shorter string first, then plain order to break
+    // ties such as "01" and "1"
+    a_rest.len().cmp(&b_rest.len()).then_with(|| a.cmp(b))
+}
+
+/// How file paths are shown in listings and results.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PathStyle {
+    /// Relative to the scan root, when the file is under it
+    #[default]
+    Relative,
+    /// Absolute, resolved against the current directory
+    Absolute,
+    /// Only the file name
+    Basename,
+}
+
+impl FromStr for PathStyle {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "relative" => Ok(PathStyle::Relative),
+            "absolute" => Ok(PathStyle::Absolute),
+            "basename" => Ok(PathStyle::Basename),
+            _ => Err(format!("unknown path style '{}'", value)),
+        }
+    }
+}
+
+impl PathStyle {
+    /// Renders `path`, a file found under `root`, in this style.
+    pub fn display(self, path: &Path, root: &Path) -> String {
+        match self {
+            PathStyle::Relative => path.strip_prefix(root).unwrap_or(path).display().to_string(),
+            PathStyle::Absolute => std::path::absolute(path)
+                .unwrap_or_else(|_| path.to_path_buf())
+                .display()
+                .to_string(),
+            PathStyle::Basename => path.file_name()
+                .map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned()),
+        }
+    }
+}
+
+/// Files found during collection, along with bookkeeping about what was left out.
+#[derive(Debug, Default)]
+pub struct CollectedFiles {
+    pub files: Vec<PathBuf>,
+    /// Files and directories skipped because they matched an exclude pattern
+    pub excluded: usize,
+    /// Files skipped because they were last modified before
+    /// [`ScanOptions::modified_after`]
+    pub stale: usize,
+    /// Files skipped for being smaller than [`ScanOptions::min_size`]
+    pub too_small: usize,
+    /// Symlinked directories that were not followed, or that led back to a
+    /// directory already collected
+    pub skipped_symlinks: Vec<PathBuf>,
+    /// Listed files that don't exist
+    pub missing: Vec<PathBuf>,
+    /// Files in `files` without a known text extension. Their content is
+    /// only checked when they are scanned, so that each file is opened once;
+    /// the ones that turn out not to be text are dropped from the report.
+    pub unsniffed: HashSet<PathBuf>,
+    /// Collection stopped at [`ScanOptions::max_files`], so there were
+    /// more files than `files` holds
+    pub limit_reached: bool,
+    /// The single entry in `files` stands for the systemd journal, which is
+    /// read through `journalctl`
+    pub from_journal: bool,
+    visited_dirs: HashSet<PathBuf>,
+    // The paths in `files`, so a file reached twice is only listed once
+    added: HashSet<PathBuf>,
+}
+
+impl CollectedFiles {
+    // Honors --extensions and --all-files. Archives are always scanned, member
+    // by member.
+    fn add_file(&mut self, path: PathBuf, options: &ScanOptions) {
+        // Both filters go by the metadata, which is only read when one is on
+        if options.modified_after.is_some() || options.min_size > 0 {
+            if let Ok(metadata) = fs::metadata(&path) {
+                if metadata.len() < options.min_size {
+                    log_skipped(options, &path, "smaller than --min-size");
+                    self.too_small += 1;
+                    return;
+                }
+                // Files whose modification time can't be read are kept
+                let modified = metadata.modified().ok();
+                if options.modified_after.is_some_and(|after| modified.is_some_and(|modified| modified < after)) {
+                    log_skipped(options, &path, "not modified recently (--modified-within)");
+                    self.stale += 1;
+                    return;
+                }
+            }
+        }
+        if self.added.contains(&path) {
+            return;
+        }
+        // Files left to be sniffed count too, even if they turn out not to be text
+        if options.max_files.is_some_and(|max_files| self.files.len() >= max_files) {
+            self.limit_reached = true;
+            return;
+        }
+        if !options.all_files && !has_known_extension(&path, &options.extra_extensions) && !is_archive(&path) {
+            self.unsniffed.insert(path.clone());
+        }
+        self.added.insert(path.clone());
+        self.files.push(path);
+        show_progress(options, self.files.len(), self.visited_dirs.len());
+    }
+
+    /// How many directories were walked to find the files.
+    pub fn dirs_visited(&self) -> usize {
+        self.visited_dirs.len()
+    }
+}
+
+fn show_progress(options: &ScanOptions, files: usize, dirs: usize) {
+    if let Some(progress) = &options.collect_progress {
+        progress.set_message(format!("Scanning directory tree: {} files in {} directories so far", files, dirs));
+    }
+}
+
+/// The systemd journal in place of log files, see [`CollectedFiles::from_journal`].
+pub fn collect_journal() -> CollectedFiles {
+    CollectedFiles {
+        files: vec![PathBuf::from(JOURNAL_PATH)],
+        from_journal: true,
+        ..CollectedFiles::default()
+    }
+}
+
+/// Takes an explicit list of files as-is, without the text file check.
+///
+/// Paths that don't exist are set aside in `missing`; the rest keep their
+/// order.
+pub fn collect_listed_files(paths: Vec<PathBuf>) -> CollectedFiles {
+    let (files, missing) = paths.into_iter().partition(|path| path.is_file());
+    CollectedFiles {
+        files,
+        missing,
+        ..CollectedFiles::default()
+    }
+}
+
+/// Collects the text files matching shell-style glob patterns such as
+/// `/var/log/**/*.log`, sorted by path in natural order.
+///
+/// Exclude patterns still apply, matched against the full path.
+pub fn collect_glob_files(patterns: &[String], options: &ScanOptions) -> Result<CollectedFiles> {
+    let mut collected = CollectedFiles::default();
+    let mut seen = HashSet::new();
+
+    'patterns: for pattern in patterns {
+        let paths = glob::glob(pattern).map_err(|e| AppError::InvalidInput(
+            format!("Invalid glob pattern '{}': {}", pattern, e)
+        ))?;
+
+        for entry in paths {
+            match entry {
+                Ok(path) => {
+                    if !path.is_file() || !seen.insert(path.clone()) {
+                        continue;
+                    }
+                    if is_excluded(options, Path::new(""), &path, false) {
+                        log_skipped(options, &path, "matches an exclude pattern");
+                        collected.excluded += 1;
+                    } else {
+                        collected.add_file(path, options);
+                        if collected.limit_reached {
+                            break 'patterns;
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{} Error accessing {}: {}",
+                        "⚠️".warn(),
+                        e.path().display(),
+                        e.error().to_string().bad());
+                }
+            }
+        }
+    }
+
+    collected.files.sort_by(|a, b| natural_cmp(&a.display().to_string(), &b.display().to_string()));
+    Ok(collected)
+}
+
+/// Collects the text files under `root`, sorted by path in natural order.
+pub fn collect_files(root: &Path, options: &ScanOptions) -> Result<CollectedFiles> {
+    collect_files_from(&[root], options)
+}
+
+/// Collects the text files under each of `roots` into a single list, sorted
+/// by path in natural order. A file reached from more than one root is
+/// listed once.
+pub fn collect_files_from<P: AsRef<Path>>(roots: &[P], options: &ScanOptions) -> Result<CollectedFiles> {
+    let mut collected = CollectedFiles::default();
+    for root in roots {
+        let root = root.as_ref();
+        if options.respect_ignore_files {
+            collect_files_walk(root, options, &mut collected)?;
+        } else {
+            collect_files_recursive(root, root, 0, options, &mut collected)?;
+        }
+    }
+    collected.files.sort_by(|a, b| natural_cmp(&a.display().to_string(), &b.display().to_string()));
+    Ok(collected)
+}
+
+// Digs the symlink out of a loop error, through the context ignore wraps around it
+fn loop_child(error: &ignore::Error) -> Option<&Path> {
+    match error {
+        ignore::Error::Loop { child, .. } => Some(child),
+        ignore::Error::WithPath { err, .. }
+        | ignore::Error::WithDepth { err, .. }
+        | ignore::Error::WithLineNumber { err, .. } => loop_child(err),
+        _ => None,
+    }
+}
+
+// The same walk as collect_files_recursive, but through ignore's walker so
+// that .gitignore and friends are honored
+fn collect_files_walk(root: &Path, options: &ScanOptions, collected: &mut CollectedFiles) -> Result<()> {
+    // Fail the same way as the plain walk when the root itself is unreadable
+    if let Err(e) = fs::read_dir(root) {
+        return Err(match e.kind() {
+            io::ErrorKind::PermissionDenied => AppError::PermissionDenied(
+                format!("Cannot access directory {}: Permission denied", root.display())
+            ),
+            _ => AppError::IoError(e),
+        });
+    }
+
+    // The walker doesn't pass the root through the filter below
+    if let Ok(canonical) = fs::canonicalize(root) {
+        collected.visited_dirs.insert(canonical);
+    }
+
+    // Directories are pruned in the walker's filter, which has to be 'static,
+    // so the bookkeeping is moved in and back out afterwards
+    let shared = Arc::new(Mutex::new(mem::take(collected)));
+    let filter_shared = Arc::clone(&shared);
+    let filter_options = options.clone();
+    let filter_root = root.to_path_buf();
+
+    let walker = WalkBuilder::new(root)
+        // Only ignore files decide what is skipped; hidden files are scanned as before
+        .hidden(false)
+        .require_git(false)
+        .follow_links(options.follow_symlinks)
+        .max_depth(options.max_depth.map(|max_depth| max_depth + 1))
+        .filter_entry(move |entry| {
+            let path = entry.path();
+            let is_dir = path.is_dir();
+            let mut collected = filter_shared.lock().unwrap();
+
+            if entry.depth() > 0 && is_excluded(&filter_options, &filter_root, path, is_dir) {
+                log_skipped(&filter_options, path, "matches an exclude pattern");
+                collected.excluded += 1;
+                return false;
+            }
+            if !is_dir {
+                return true;
+            }
+            if entry.depth() > 0 && entry.path_is_symlink() && !filter_options.follow_symlinks {
+                log_skipped(&filter_options, path, "symlinked directory (see --follow-symlinks)");
+                collected.skipped_symlinks.push(path.to_path_buf());
+                return false;
+            }
+            // The walker would list the directory without descending into it
+            if filter_options.max_depth.is_some_and(|max_depth| entry.depth() > max_depth) {
+                log_skipped(&filter_options, path, "deeper than --max-depth");
+                return false;
+            }
+            // Remember every directory entered so a symlink back up the tree can't loop
+            if let Ok(canonical) = fs::canonicalize(path) {
+                if !collected.visited_dirs.insert(canonical) {
+                    log_skipped(&filter_options, path, "directory already visited");
+                    collected.skipped_symlinks.push(path.to_path_buf());
+                    return false;
+                }
+            }
+            log_entered(&filter_options, path);
+            true
+        })
+        .build();
+
+    for entry in walker {
+        match entry {
+            Ok(entry) => {
+                if entry.path().is_file() {
+                    let mut collected = shared.lock().unwrap();
+                    collected.add_file(entry.into_path(), options);
+                    // Stop walking as soon as there is one file too many
+                    if collected.limit_reached {
+                        break;
+                    }
+                }
+            }
+            Err(e) => {
+                // The walker catches links back to an ancestor before the filter sees them
+                if let Some(child) = loop_child(&e) {
+                    log_skipped(options, child, "directory already visited");
+                    shared.lock().unwrap().skipped_symlinks.push(child.to_path_buf());
+                } else if e.io_error().is_some_and(|io_error| io_error.kind() == io::ErrorKind::PermissionDenied) {
+                    eprintln!("{} Skipping {}",
+                        "⚠️".warn(),
+                        e.to_string().warn());
+                } else {
+                    eprintln!("{} Error while walking {}: {}",
+                        "⚠️".warn(),
+                        root.display(),
+                        e.to_string().bad());
+                }
+            }
+        }
+    }
+
+    *collected = mem::take(&mut *shared.lock().unwrap());
+    Ok(())
+}
+
+/// Walks `dir_path` and adds every text file that isn't excluded to `collected`.
+///
+/// `root` is the directory the scan started from, which exclude patterns are
+/// matched relative to, and `depth` is how many levels below it `dir_path`
+/// is. Unreadable subdirectories are reported and skipped; only a failure to
+/// read `dir_path` itself is returned as an error.
+pub fn collect_files_recursive(dir_path: &Path, root: &Path, depth: usize, options: &ScanOptions, collected: &mut CollectedFiles) -> Result<()> {
+    if collected.limit_reached {
+        return Ok(());
+    }
+    // Remember every directory entered so a symlink back up the tree can't loop
+    if let Ok(canonical) = fs::canonicalize(dir_path) {
+        if !collected.visited_dirs.insert(canonical) {
+            log_skipped(options, dir_path, "directory already visited");
+            collected.skipped_symlinks.push(dir_path.to_path_buf());
+            return Ok(());
+        }
+    }
+    log_entered(options, dir_path);
+    show_progress(options, collected.files.len(), collected.visited_dirs.len());
+
+    match fs::read_dir(dir_path) {
+        Ok(entries) => {
+            for entry in entries {
+                if collected.limit_reached {
+                    break;
+                }
+                match entry {
+                    Ok(entry) => {
+                        let path = entry.path();
+                        if path.is_file() {
+                            if is_excluded(options, root, &path, false) {
+                                log_skipped(options, &path, "matches an exclude pattern");
+                                collected.excluded += 1;
+                            } else {
+                                collected.add_file(path, options);
+                            }
+                        } else if path.is_dir() {
+                            let is_symlink = entry.file_type().is_ok_and(|file_type| file_type.is_symlink());
+
+                            // Excluded directories are not descended into at all
+                            if is_excluded(options, root, &path, true) {
+                                log_skipped(options, &path, "matches an exclude pattern");
+                                collected.excluded += 1;
+                            } else if is_symlink && !options.follow_symlinks {
+                                log_skipped(options, &path, "symlinked directory (see --follow-symlinks)");
+                                collected.skipped_symlinks.push(path);
+                            } else if options.max_depth.is_some_and(|max_depth| depth >= max_depth) {
+                                log_skipped(options, &path, "deeper than --max-depth");
+                                continue;
+                            // If we can't access a subdirectory, log it and continue
+                            } else if let Err(e) = collect_files_recursive(&path, root, depth + 1, options, collected) {
+                                match e {
+                                    AppError::PermissionDenied(_) => {
+                                        eprintln!("{} Skipping directory {}: {}",
+                                            "⚠️".warn(),
+                                            path.display(),
+                                            "Permission denied".warn());
+                                    },
+                                    _ => {
+                                        eprintln!("{} Error accessing directory {}: {}",
+                                            "⚠️".warn(),
+                                            path.display(),
+                                            e.to_string().bad());
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        match e.kind() {
+                            io::ErrorKind::PermissionDenied => {
+                                eprintln!("{} Skipping entry in {}: {}",
+                                    "⚠️".warn(),
+                                    dir_path.display(),
+                                    "Permission denied".warn());
+                            },
+                            _ => {
+                                eprintln!("{} Error accessing entry in {}: {}",
+                                    "⚠️".warn(),
+                                    dir_path.display(),
+                                    e.to_string().bad());
+                            }
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+        Err(e) => {
+            match e.kind() {
+                io::ErrorKind::PermissionDenied => {
+                    Err(AppError::PermissionDenied(
+                        format!("Cannot access directory {}: Permission denied", dir_path.display())
+                    ))
+                },
+                _ => Err(AppError::IoError(e))
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use serde::Deserialize;
+use rustwatch::{normalize_message, AppError, FileReport, LogEntry, Result};
+
+// The parts of a --format json report needed for a comparison
+#[derive(Deserialize)]
+struct SavedReport {
+    files: Vec<SavedFile>,
+}
+
+#[derive(Deserialize)]
+struct SavedFile {
+    path: String,
+    errors: Vec<LogEntry>,
+}
+
+// Errors are the same when they are in the same file and only differ in
+// numbers, ids and addresses
+type MessageKey = (String, String);
+
+fn message_key(file_name: &str, content: &str) -> MessageKey {
+    (file_name.to_string(), normalize_message(content))
+}
+
+/// The errors of an earlier `--format json` report.
+pub struct PreviousScan {
+    // The first line reported for each message, to show what disappeared
+    messages: HashMap<MessageKey, String>,
+}
+
+/// How the current results differ from a previous scan.
+pub struct Comparison<'a> {
+    /// Errors whose message wasn't in the previous report, by file
+    pub new: Vec<(&'a str, Vec<&'a LogEntry>)>,
+    /// File and line of each previous message that no longer occurs
+    pub resolved: Vec<(String, String)>,
+    /// Errors whose message was reported before too
+    pub persisting: usize,
+}
+
+impl PreviousScan {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).map_err(|e| AppError::InvalidInput(
+            format!("Cannot read previous report {}: {}", path.display(), e)
+        ))?;
+        let report: SavedReport = serde_json::from_str(&text).map_err(|e| AppError::InvalidInput(
+            format!("{} is not a --format json report: {}", path.display(), e)
+        ))?;
+
+        let mut messages = HashMap::new();
+        for file in report.files {
+            for entry in file.errors {
+                messages.entry(message_key(&file.path, &entry.content)).or_insert(entry.content);
+            }
+        }
+        Ok(Self { messages })
+    }
+
+    pub fn compare<'a>(&self, errors_by_file: &'a [(String, FileReport)]) -> Comparison<'a> {
+        let mut seen = HashSet::new();
+        let mut new = Vec::new();
+        let mut persisting = 0;
+        for (file_name, report) in errors_by_file {
+            let mut new_entries = Vec::new();
+            for entry in &report.entries {
+                let key = message_key(file_name, &entry.content);
+                if self.messages.contains_key(&key) {
+                    persisting += 1;
+                } else {
+                    new_entries.push(entry);
+                }
+                seen.insert(key);
+            }
+            if !new_entries.is_empty() {
+                new.push((file_name.as_str(), new_entries));
+            }
+        }
+
+        let mut resolved: Vec<_> = self.messages.iter()
+            .filter(|(key, _)| !seen.contains(*key))
+            .map(|((file_name, _), content)| (file_name.clone(), content.clone()))
+            .collect();
+        resolved.sort();
+
+        Comparison { new, resolved, persisting }
+    }
+}
@@ -0,0 +1,94 @@
+use std::env;
+use std::fs;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use clap::ArgMatches;
+use clap::parser::ValueSource;
+use clap::ValueEnum;
+use serde::Deserialize;
+use rustwatch::{AppError, Result};
+
+use crate::{parse_size, Cli, OutputFormat};
+
+const CONFIG_FILE_NAME: &str = "rustwatch.toml";
+
+/// Defaults read from a `rustwatch.toml`, applied wherever the matching flag
+/// wasn't given on the command line.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    keywords: Option<Vec<String>>,
+    excludes: Option<Vec<String>>,
+    /// Same syntax as --max-size, e.g. "50M"
+    max_size: Option<String>,
+    threads: Option<NonZeroUsize>,
+    extensions: Option<Vec<String>>,
+    all_files: Option<bool>,
+    format: Option<String>,
+}
+
+fn home_dir() -> Option<PathBuf> {
+    let var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    env::var_os(var).map(PathBuf::from)
+}
+
+// The explicit path if given, otherwise the first rustwatch.toml found in
+// the current directory and then the home directory
+fn find_config(explicit: Option<&Path>) -> Option<PathBuf> {
+    if let Some(path) = explicit {
+        return Some(path.to_path_buf());
+    }
+    [Some(PathBuf::from(CONFIG_FILE_NAME)), home_dir().map(|home| home.join(CONFIG_FILE_NAME))]
+        .into_iter()
+        .flatten()
+        .find(|path| path.is_file())
+}
+
+impl Config {
+    /// Loads the config file, returning `None` when there is none to load.
+    ///
+    /// An explicit path that can't be read is an error; so is a file that
+    /// doesn't parse, wherever it was found.
+    pub fn load(explicit: Option<&Path>) -> Result<Option<Config>> {
+        let Some(path) = find_config(explicit) else {
+            return Ok(None);
+        };
+
+        let text = fs::read_to_string(&path).map_err(|e| AppError::InvalidInput(
+            format!("Cannot read config file {}: {}", path.display(), e)
+        ))?;
+        toml::from_str(&text).map(Some).map_err(|e| AppError::InvalidInput(
+            format!("Invalid config file {}: {}", path.display(), e)
+        ))
+    }
+
+    /// Fills in every option the user didn't set on the command line.
+    pub fn apply(self, cli: &mut Cli, matches: &ArgMatches) -> Result<()> {
+        let from_command_line = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+        if let Some(keywords) = self.keywords.filter(|_| !from_command_line("keywords")) {
+            cli.keywords = keywords;
+        }
+        if let Some(excludes) = self.excludes.filter(|_| !from_command_line("excludes")) {
+            cli.excludes = excludes;
+        }
+        if let Some(max_size) = self.max_size.filter(|_| !from_command_line("max_size")) {
+            cli.max_size = parse_size(&max_size)
+                .map_err(|e| AppError::InvalidInput(format!("max-size in config file: {}", e)))?;
+        }
+        if let Some(threads) = self.threads.filter(|_| !from_command_line("threads")) {
+            cli.threads = Some(threads);
+        }
+        if let Some(extensions) = self.extensions.filter(|_| !from_command_line("extensions")) {
+            cli.extensions = extensions;
+        }
+        if let Some(all_files) = self.all_files.filter(|_| !from_command_line("all_files")) {
+            cli.all_files = all_files;
+        }
+        if let Some(format) = self.format.filter(|_| !from_command_line("format")) {
+            cli.format = OutputFormat::from_str(&format, true)
+                .map_err(|e| AppError::InvalidInput(format!("format in config file: {}", e)))?;
+        }
+        Ok(())
+    }
+}
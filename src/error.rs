@@ -0,0 +1,42 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Custom error type for the application
+#[derive(Debug)]
+pub enum AppError {
+    IoError(io::Error),
+    PermissionDenied(String),
+    InvalidInput(String),
+    FileProcessingError { path: PathBuf, error: String },
+    FileSizeError(String),
+    TimeoutError(String),
+    EncodingError(String),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::IoError(err) => write!(f, "IO error: {}", err),
+            AppError::PermissionDenied(msg) => write!(f, "Permission denied: {}", msg),
+            AppError::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
+            AppError::FileProcessingError { path, error } => {
+                write!(f, "Error processing file {:?}: {}", path, error)
+            },
+            AppError::FileSizeError(msg) => write!(f, "File size error: {}", msg),
+            AppError::TimeoutError(msg) => write!(f, "Operation timed out: {}", msg),
+            AppError::EncodingError(msg) => write!(f, "Encoding error: {}", msg),
+        }
+    }
+}
+
+impl Error for AppError {}
+
+impl From<io::Error> for AppError {
+    fn from(err: io::Error) -> Self {
+        AppError::IoError(err)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, AppError>;
@@ -0,0 +1,247 @@
+use std::borrow::Cow;
+use std::fs;
+use std::io::{self, BufRead, Read, Seek, SeekFrom};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::archive::is_archive;
+use crate::bytes::escape_bytes;
+use crate::collect::is_text_file_with;
+use crate::scanner::{is_gzip_file, is_read_stopped, read_line_bounded, stored_line, truncate_line, LogEntry, ScanOptions};
+
+// Identifies the file behind a path so rotation can be detected
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+// How much of what was appended to a file is read at a time
+const READ_CHUNK: u64 = 64 * 1024;
+
+// A line read from a followed file: its number, its bytes, and whether it
+// was cut short
+type NewLine = (usize, Vec<u8>, bool);
+
+struct FollowedFile {
+    path: PathBuf,
+    display_path: String,
+    file: Option<fs::File>,
+    file_id: Option<u64>,
+    offset: u64,
+    line_number: usize,
+    // Bytes after the last newline, held back until the line is complete,
+    // and whether any were dropped for going past --max-line-length
+    partial: Vec<u8>,
+    partial_cut: bool,
+}
+
+impl FollowedFile {
+    fn open(path: &Path, display_path: String) -> io::Result<Self> {
+        let mut followed = Self {
+            path: path.to_path_buf(),
+            display_path,
+            file: None,
+            file_id: None,
+            offset: 0,
+            line_number: 0,
+            partial: Vec::new(),
+            partial_cut: false,
+        };
+        followed.reopen()?;
+
+        // Start at the current end of the file. Counting the lines already
+        // there would mean reading it all again, so new lines are numbered
+        // from here.
+        let file = followed.file.as_mut().expect("file was just opened");
+        followed.offset = file.seek(SeekFrom::End(0))?;
+        Ok(followed)
+    }
+
+    fn reopen(&mut self) -> io::Result<()> {
+        let file = fs::File::open(&self.path)?;
+        self.file_id = file_id(&file.metadata()?);
+        self.file = Some(file);
+        self.offset = 0;
+        self.line_number = 0;
+        self.partial.clear();
+        self.partial_cut = false;
+        Ok(())
+    }
+
+    // Returns the complete lines in the next chunk appended since the last
+    // read, each with whether it was cut at `max_len`, and whether there is
+    // more to read
+    fn read_new_lines(&mut self, max_len: usize) -> io::Result<(Vec<NewLine>, bool)> {
+        let metadata = fs::metadata(&self.path)?;
+
+        // A replaced or truncated file means the log was rotated
+        let rotated = file_id(&metadata) != self.file_id || metadata.len() < self.offset;
+        if rotated || self.file.is_none() {
+            self.reopen()?;
+        }
+
+        let Some(file) = self.file.as_mut() else {
+            return Ok((Vec::new(), false));
+        };
+        if metadata.len() == self.offset {
+            return Ok((Vec::new(), false));
+        }
+
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut appended = Vec::new();
+        file.take(READ_CHUNK).read_to_end(&mut appended)?;
+        self.offset += appended.len() as u64;
+        let more = !appended.is_empty() && self.offset < metadata.len();
+
+        let mut lines = Vec::new();
+        for piece in appended.split_inclusive(|&b| b == b'\n') {
+            let (text, complete) = match piece.strip_suffix(b"\n") {
+                Some(text) => (text, true),
+                None => (piece, false),
+            };
+            let room = max_len.saturating_sub(self.partial.len());
+            self.partial_cut |= text.len() > room;
+            self.partial.extend_from_slice(&text[..text.len().min(room)]);
+            if complete {
+                self.line_number += 1;
+                let mut line = mem::take(&mut self.partial);
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+                lines.push((self.line_number, line, mem::take(&mut self.partial_cut)));
+            }
+        }
+        Ok((lines, more))
+    }
+}
+
+/// Watches files for appended lines, like `tail -f`.
+///
+/// Each file is read from its end at the time the follower is created, and
+/// the lines appended after that are numbered from 1.
+/// Files that shrink or are replaced (log rotation) are reopened and read
+/// from the start. Gzip files are not followed since they don't grow.
+pub struct Follower {
+    files: Vec<FollowedFile>,
+    options: ScanOptions,
+}
+
+impl Follower {
+    /// Starts following `paths`, labelling matches with paths shown relative to `root` or in
+    /// another [`PathStyle`](crate::PathStyle).
+    pub fn new(root: &Path, paths: &[PathBuf], options: &ScanOptions) -> Self {
+        let files = paths.iter()
+            .filter(|path| !is_gzip_file(path))
+            // Archives are only ever read whole
+            .filter(|path| !is_archive(path))
+            // Collection leaves some files to be sniffed by the scan, so check again here
+            .filter(|path| options.all_files || is_text_file_with(path, &options.extra_extensions, &options.sniff))
+            .filter_map(|path| {
+                let display_path = options.path_style.display(path, root);
+                FollowedFile::open(path, display_path).ok()
+            })
+            .collect();
+
+        Self {
+            files,
+            options: options.clone(),
+        }
+    }
+
+    /// Number of files being followed.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Reads whatever was appended since the last poll and returns the new
+    /// matches, labelled with their display path.
+    pub fn poll(&mut self) -> Vec<(String, LogEntry)> {
+        let mut matches = Vec::new();
+
+        for followed in &mut self.files {
+            // Files that vanish temporarily (e.g. mid-rotation) are retried on the next poll
+            while let Ok((lines, more)) = followed.read_new_lines(self.options.max_line_length) {
+                for (line_number, line, cut) in lines {
+                    if let Some(entry) = followed_match(&self.options, line_number, &line, cut) {
+                        matches.push((followed.display_path.clone(), entry));
+                    }
+                }
+                if !more {
+                    break;
+                }
+            }
+        }
+
+        matches
+    }
+}
+
+// The entry for a newly read line if it matches, where `cut` says the reader
+// already cut the line short; lines without a timestamp are dated to when
+// they were read
+fn followed_match(options: &ScanOptions, line_number: usize, raw_line: &[u8], cut: bool) -> Option<LogEntry> {
+    let line = if options.byte_patterns.is_empty() {
+        String::from_utf8_lossy(raw_line)
+    } else {
+        Cow::Owned(escape_bytes(raw_line))
+    };
+    let (line, truncated) = truncate_line(&line, options.max_line_length);
+    let timestamp = options.match_line(line).filter(|_| !options.is_suppressed(line))?;
+    Some(LogEntry {
+        line_number,
+        timestamp: timestamp.or_else(|| Some(SystemTime::now())),
+        severity: options.line_severity(line),
+        content: stored_line(&options.display_content(line), truncated || cut),
+        before: Vec::new(),
+        after: Vec::new(),
+        occurrences: 1,
+        last_line_number: None,
+        matched_pattern: options.matched_pattern(line),
+        cursor: None,
+        continuation_lines: 0,
+    })
+}
+
+/// Matches the lines of `reader`, such as stdin fed by `journalctl -f`, as
+/// they arrive, handing each match to `on_match` right away.
+///
+/// Reads until the end of input, or until `options.stop` is set or the
+/// deadline passes, which is noticed once more input is in. Invalid UTF-8
+/// is replaced (or escaped, with byte patterns) rather than rejected, since a
+/// stream can't be skipped like a binary file. Returns the number of lines
+/// read.
+pub fn follow_reader<R, F>(mut reader: R, options: &ScanOptions, mut on_match: F) -> io::Result<usize>
+where
+    R: BufRead,
+    F: FnMut(LogEntry) -> io::Result<()>,
+{
+    let mut line_number = 0;
+    let mut buffer = Vec::new();
+    loop {
+        buffer.clear();
+        // Input without newlines is cut at --max-line-length rather than
+        // held in memory whole
+        let cut = match read_line_bounded(&mut reader, &mut buffer, options.max_line_length, || options.should_stop()) {
+            Ok(Some(cut)) => cut,
+            Ok(None) => break,
+            Err(e) if is_read_stopped(&e) => break,
+            Err(e) => return Err(e),
+        };
+        line_number += 1;
+        if let Some(entry) = followed_match(options, line_number, &buffer, cut) {
+            on_match(entry)?;
+        }
+    }
+    Ok(line_number)
+}
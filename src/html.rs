@@ -0,0 +1,169 @@
+use std::io::{self, Write};
+use std::time::Duration;
+use chrono::Local;
+use rustwatch::{FileReport, LogEntry, ScanOptions, ScanStats, Severity};
+
+const STYLE: &str = r#"
+body { font-family: -apple-system, "Segoe UI", Roboto, sans-serif; margin: 2rem; color: #1f2328; background: #f6f8fa; }
+h1 { margin-bottom: 0.2rem; }
+.generated { color: #656d76; margin-top: 0; }
+.dashboard { display: flex; flex-wrap: wrap; gap: 1rem; margin: 1.5rem 0; }
+.card { background: #fff; border: 1px solid #d0d7de; border-radius: 8px; padding: 0.8rem 1.2rem; min-width: 9rem; }
+.card .value { font-size: 1.6rem; font-weight: 600; }
+.card .label { color: #656d76; font-size: 0.85rem; }
+.card.alert .value { color: #cf222e; }
+details { background: #fff; border: 1px solid #d0d7de; border-radius: 8px; margin-bottom: 0.8rem; }
+summary { cursor: pointer; padding: 0.7rem 1rem; font-weight: 600; }
+summary .count { color: #cf222e; font-weight: normal; margin-left: 0.5rem; }
+table { border-collapse: collapse; width: 100%; font-family: ui-monospace, SFMono-Regular, Menlo, monospace; font-size: 0.85rem; }
+td, th { text-align: left; padding: 0.25rem 1rem; border-top: 1px solid #eaeef2; vertical-align: top; }
+td.line { color: #656d76; text-align: right; white-space: nowrap; }
+td.time { white-space: nowrap; color: #0969da; }
+td.content { white-space: pre-wrap; word-break: break-all; }
+tr.context td { color: #8c959f; }
+tr.after td.content { border-left: 3px solid #d0d7de; }
+tr.warn td.content { color: #9a6700; }
+tr.critical td.content { color: #a40e26; font-weight: 600; }
+mark { background: #fff8c5; color: inherit; border-radius: 2px; }
+.repeats { color: #8250df; }
+.empty { color: #1a7f37; font-size: 1.1rem; }
+.oversized { color: #9a6700; }
+"#;
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Escapes a line and wraps the keyword occurrences in <mark>
+fn highlight(line: &str, options: &ScanOptions) -> String {
+    let mut html = String::with_capacity(line.len() + 32);
+    let mut last = 0;
+    for range in options.keyword_ranges(line) {
+        html.push_str(&escape(&line[last..range.start]));
+        html.push_str("<mark>");
+        html.push_str(&escape(&line[range.clone()]));
+        html.push_str("</mark>");
+        last = range.end;
+    }
+    html.push_str(&escape(&line[last..]));
+    html
+}
+
+fn write_card(out: &mut dyn Write, label: &str, value: &str, alert: bool) -> io::Result<()> {
+    writeln!(out, r#"<div class="card{}"><div class="value">{}</div><div class="label">{}</div></div>"#,
+        if alert { " alert" } else { "" },
+        escape(value),
+        escape(label))
+}
+
+// `position` is "before" or "after" the match, so the two can be styled apart
+fn write_context_row(out: &mut dyn Write, position: &str, line_number: usize, line: &str) -> io::Result<()> {
+    writeln!(out, r#"<tr class="context {}"><td class="line">{}</td><td></td><td class="content">{}</td></tr>"#,
+        position,
+        line_number,
+        escape(line))
+}
+
+fn write_entry(out: &mut dyn Write, entry: &LogEntry, options: &ScanOptions) -> io::Result<()> {
+    let first_before = entry.line_number - entry.before.len();
+    for (i, line) in entry.before.iter().enumerate() {
+        write_context_row(out, "before", first_before + i, line)?;
+    }
+
+    let class = match entry.severity {
+        Some(Severity::Critical) => "critical",
+        Some(Severity::Warn) => "warn",
+        _ => "match",
+    };
+    let repeats = match entry.last_line_number {
+        Some(last) if entry.occurrences > 1 =>
+            format!(r#" <span class="repeats">(×{}, last on line {})</span>"#, entry.occurrences, last),
+        _ => String::new(),
+    };
+    writeln!(out, r#"<tr class="{}"><td class="line">{}</td><td class="time">{}</td><td class="content">{}{}</td></tr>"#,
+        class,
+        entry.line_number,
+        escape(&entry.format_timestamp()),
+        highlight(&entry.content, options),
+        repeats)?;
+
+    for (i, line) in entry.after.iter().enumerate() {
+        write_context_row(out, "after", entry.end_line_number() + 1 + i, line)?;
+    }
+    Ok(())
+}
+
+/// Writes a self-contained HTML page with a summary dashboard and one
+/// collapsible section per file.
+pub fn print_html_report(
+    out: &mut dyn Write,
+    errors_by_file: &[(String, FileReport)],
+    stats: &ScanStats,
+    duration: Duration,
+    options: &ScanOptions,
+) -> io::Result<()> {
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, r#"<html lang="en"><head><meta charset="utf-8">"#)?;
+    writeln!(out, r#"<meta name="viewport" content="width=device-width, initial-scale=1">"#)?;
+    writeln!(out, "<title>RustWatch Report</title>")?;
+    writeln!(out, "<style>{}</style></head><body>", STYLE)?;
+    writeln!(out, "<h1>🦊 RustWatch Report</h1>")?;
+    writeln!(out, r#"<p class="generated">Generated {} by RustWatch {}</p>"#,
+        Local::now().format("%Y-%m-%d %H:%M:%S"),
+        env!("CARGO_PKG_VERSION"))?;
+
+    writeln!(out, r#"<div class="dashboard">"#)?;
+    write_card(out, "Files scanned", &stats.processed_files.to_string(), false)?;
+    write_card(out, "Matches found", &stats.total_errors.to_string(), stats.total_errors > 0)?;
+    write_card(out, "Files with matches", &errors_by_file.len().to_string(), !errors_by_file.is_empty())?;
+    write_card(out, "Files skipped", &stats.skipped_files.to_string(), false)?;
+    write_card(out, "Scan time", &format!("{} ms", duration.as_millis()), false)?;
+    writeln!(out, "</div>")?;
+
+    if !stats.oversized_files.is_empty() {
+        writeln!(out, r#"<p class="oversized">Skipped for exceeding the size limit: {}</p>"#,
+            escape(&stats.oversized_files.join(", ")))?;
+    }
+    if !stats.timed_out_files.is_empty() {
+        writeln!(out, r#"<p class="oversized">Timed out (rerun with --timeout 0 to include them): {}</p>"#,
+            escape(&stats.timed_out_files.join(", ")))?;
+    }
+
+    if errors_by_file.is_empty() {
+        writeln!(out, r#"<p class="empty">✅ No matches found in processed files.</p>"#)?;
+    }
+
+    for (file_name, report) in errors_by_file {
+        let mut truncation_note = if report.is_truncated() {
+            format!(", showing first {}", report.entries.len())
+        } else {
+            String::new()
+        };
+        if report.changed_during_scan {
+            truncation_note.push_str(", possibly incomplete: file changed during scan");
+        }
+        writeln!(out, r#"<details><summary>{}<span class="count">{} {}{}</span></summary>"#,
+            escape(file_name),
+            report.match_count,
+            if report.match_count == 1 { "match" } else { "matches" },
+            truncation_note)?;
+        writeln!(out, "<table><tr><th>Line</th><th>Time</th><th>Content</th></tr>")?;
+        for entry in &report.entries {
+            write_entry(out, entry, options)?;
+        }
+        writeln!(out, "</table></details>")?;
+    }
+
+    writeln!(out, "</body></html>")
+}
@@ -0,0 +1,153 @@
+use std::io::BufReader;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use chrono::{DateTime, Local};
+use serde_json::Value;
+
+use crate::error::{AppError, Result};
+use crate::jsonl::field_text;
+use crate::scanner::{is_read_stopped, read_line_bounded, EntryCollector, FileReport, ScanOptions};
+
+/// The path the systemd journal is reported under.
+pub const JOURNAL_PATH: &str = "journald";
+
+// Room for an entry's other fields on top of its message, which is cut to
+// --max-line-length once the entry is parsed
+const ENTRY_FIELDS_LENGTH: usize = 64 * 1024;
+
+/// Whether `journalctl` can be run on this system.
+pub fn journal_available() -> bool {
+    Command::new("journalctl")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+fn journal_error(error: impl ToString) -> AppError {
+    AppError::FileProcessingError {
+        path: Path::new(JOURNAL_PATH).to_path_buf(),
+        error: error.to_string(),
+    }
+}
+
+// journalctl takes "@" followed by seconds since the epoch
+fn journal_time(time: SystemTime) -> String {
+    format!("@{}", time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs())
+}
+
+/// Reads the journal through `journalctl` and matches its entries like the
+/// lines of a log file.
+///
+/// Each entry is matched as a line in the form of `journalctl -o
+/// short-iso-precise`, so timestamps, `since` and `until` work as usual, and
+/// matches carry the entry's cursor. Line numbers count journal entries.
+///
+/// The per-file `timeout` doesn't apply: the journal is read as a single
+/// stream, which on most systems takes far longer than any one log file.
+/// Instead, reading stops when `options.should_stop()` says so, and the
+/// report holds the entries read until then.
+pub(crate) fn scan_journal(options: &ScanOptions) -> Result<FileReport> {
+    let mut command = Command::new("journalctl");
+    command.args(["--no-pager", "--output", "json"]);
+    if let Some(since) = options.since {
+        command.arg(format!("--since={}", journal_time(since)));
+    }
+    if let Some(until) = options.until {
+        command.arg(format!("--until={}", journal_time(until)));
+    }
+    // journalctl's own hints, e.g. about missing permissions, go straight to stderr
+    let mut child = command.stdout(Stdio::piped()).spawn().map_err(journal_error)?;
+
+    let result = read_entries(&mut child, options);
+    if result.is_err() {
+        // Don't leave journalctl blocked on a full pipe
+        let _ = child.kill();
+    }
+    let status = child.wait().map_err(journal_error)?;
+    let report = result?;
+    // journalctl fails when it is killed for stopping early
+    if !status.success() && !options.should_stop() {
+        return Err(journal_error(format!("journalctl failed ({})", status)));
+    }
+    Ok(report)
+}
+
+fn read_entries(child: &mut Child, options: &ScanOptions) -> Result<FileReport> {
+    let stdout = child.stdout.take().ok_or_else(|| journal_error("no output from journalctl"))?;
+    let mut reader = BufReader::new(stdout);
+    let mut collector = EntryCollector::new(options, None);
+    let mut bytes_read = 0;
+    let mut lines_read = 0;
+    let mut line = Vec::new();
+    // Messages that aren't valid UTF-8 come as arrays of numbers, up to four
+    // bytes for each of theirs
+    let max_len = options.max_line_length.saturating_mul(4).saturating_add(ENTRY_FIELDS_LENGTH);
+
+    loop {
+        line.clear();
+        let truncated = match read_line_bounded(&mut reader, &mut line, max_len, || options.should_stop()) {
+            Ok(Some(truncated)) => truncated,
+            Ok(None) => break,
+            Err(e) if is_read_stopped(&e) => {
+                let _ = child.kill();
+                break;
+            }
+            Err(e) => return Err(journal_error(e)),
+        };
+        bytes_read += line.len() as u64 + 1;
+        lines_read += 1;
+        // An entry cut short can't be parsed, so it is skipped like anything
+        // else that isn't an entry
+        if truncated {
+            continue;
+        }
+        let Some(entry) = serde_json::from_slice::<Value>(&line).ok().filter(Value::is_object) else {
+            continue;
+        };
+
+        let matches_before = collector.entries.len();
+        collector.push_line(lines_read, &entry_line(&entry));
+        if collector.entries.len() > matches_before {
+            if let Some(matched) = collector.entries.last_mut() {
+                matched.cursor = field_text(&entry, "__CURSOR").map(|cursor| cursor.into_owned());
+            }
+        }
+    }
+
+    let mut report = collector.finish();
+    report.bytes_read = bytes_read;
+    report.lines_read = lines_read;
+    Ok(report)
+}
+
+// "2024-06-20T12:34:56.789012+02:00 host sshd[812]: message"
+fn entry_line(entry: &Value) -> String {
+    let timestamp = field_text(entry, "__REALTIME_TIMESTAMP")
+        .and_then(|micros| micros.parse::<u64>().ok())
+        .map(|micros| DateTime::<Local>::from(UNIX_EPOCH + Duration::from_micros(micros)))
+        .map(|time| time.format("%Y-%m-%dT%H:%M:%S%.6f%:z").to_string())
+        .unwrap_or_default();
+    let host = field_text(entry, "_HOSTNAME").unwrap_or_default();
+    let identifier = field_text(entry, "SYSLOG_IDENTIFIER")
+        .or_else(|| field_text(entry, "_COMM"))
+        .unwrap_or_default();
+    let pid = field_text(entry, "_PID").map(|pid| format!("[{}]", pid)).unwrap_or_default();
+    format!("{} {} {}{}: {}", timestamp, host, identifier, pid, message(entry))
+}
+
+// Messages that aren't valid UTF-8 come as an array of bytes
+fn message(entry: &Value) -> String {
+    match entry.get("MESSAGE") {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(bytes)) => {
+            let bytes: Vec<u8> = bytes.iter()
+                .filter_map(|byte| byte.as_u64().and_then(|byte| u8::try_from(byte).ok()))
+                .collect();
+            String::from_utf8_lossy(&bytes).into_owned()
+        }
+        _ => String::new(),
+    }
+}
@@ -0,0 +1,61 @@
+use std::borrow::Cow;
+use std::str::FromStr;
+use serde_json::Value;
+
+/// A `field=value` condition on log lines that are JSON objects.
+///
+/// `field` may name a nested field with dots, as in `error.kind`. The
+/// field's value is compared as text, so `status=500` matches both the
+/// number and the string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JsonFieldMatch {
+    pub field: String,
+    pub value: String,
+}
+
+impl FromStr for JsonFieldMatch {
+    type Err = String;
+
+    fn from_str(spec: &str) -> std::result::Result<Self, Self::Err> {
+        match spec.split_once('=') {
+            Some((field, value)) if !field.is_empty() => Ok(Self {
+                field: field.to_string(),
+                value: value.to_string(),
+            }),
+            _ => Err(format!("expected FIELD=VALUE, got '{}'", spec)),
+        }
+    }
+}
+
+impl JsonFieldMatch {
+    pub(crate) fn matches(&self, object: &Value, case_sensitive: bool) -> bool {
+        field_text(object, &self.field).is_some_and(|text| if case_sensitive {
+            text == self.value
+        } else {
+            text.to_lowercase() == self.value.to_lowercase()
+        })
+    }
+}
+
+/// Parses a line as a JSON object, or returns `None` for anything else so
+/// the line can be treated as plain text.
+pub(crate) fn parse_object(line: &str) -> Option<Value> {
+    // Most plain text lines are ruled out without running the parser
+    if !line.trim_start().starts_with('{') {
+        return None;
+    }
+    serde_json::from_str(line).ok().filter(Value::is_object)
+}
+
+/// The text of a (possibly nested) field: strings as they are, other values
+/// as JSON.
+pub(crate) fn field_text<'v>(object: &'v Value, field: &str) -> Option<Cow<'v, str>> {
+    // A key containing dots wins over the nested path it looks like
+    let value = object.get(field).or_else(|| {
+        field.split('.').try_fold(object, |value, key| value.get(key))
+    })?;
+    Some(match value {
+        Value::String(text) => Cow::Borrowed(text.as_str()),
+        other => Cow::Owned(other.to_string()),
+    })
+}
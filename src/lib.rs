@@ -0,0 +1,421 @@
+//! The scanning engine behind the `rustwatch` command-line tool.
+//!
+//! [`scan_directory`] walks a directory, picks out the text log files and
+//! scans them in parallel for lines matching a [`ScanOptions`]. The individual
+//! steps ([`collect_files`], [`scan_files`] and [`process_log_file`]) are
+//! public as well for callers that need to hook in between them, and
+//! [`scan_files_streaming`] delivers results file by file as they finish.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use humansize::{format_size, BINARY};
+use rayon::prelude::*;
+use serde::Serialize;
+
+mod archive;
+mod baseline;
+mod bytes;
+mod checkpoint;
+mod collect;
+mod error;
+mod follow;
+mod journal;
+mod jsonl;
+mod normalize;
+mod scanner;
+mod state;
+mod syslog;
+mod theme;
+mod timestamp;
+mod wildcard;
+
+use collect::log_skipped;
+pub use archive::{is_archive, member_path};
+pub use baseline::Baseline;
+pub use bytes::{escape_bytes, BytePattern};
+pub use checkpoint::Checkpoint;
+pub use collect::{
+    build_exclude_set, collect_files, collect_files_from, collect_files_recursive, collect_glob_files, collect_journal, collect_listed_files,
+    is_text_file, is_text_file_sniffing, natural_cmp, CollectedFiles, PathStyle, SniffSettings,
+    MAX_NON_ASCII_RATIO, MAX_NULL_RATIO, SNIFF_LEN,
+};
+pub use error::{AppError, Result};
+pub use follow::{follow_reader, Follower};
+pub use journal::{journal_available, JOURNAL_PATH};
+pub use jsonl::JsonFieldMatch;
+pub use normalize::normalize_message;
+pub use scanner::{
+    detect_severity, process_log_file, ContinuationStyle, FileReport, LevelCounts, LogEntry, ScanOptions, Severity,
+    DEFAULT_RETRIES, MAX_FILE_SIZE, MAX_LINE_LENGTH, MIN_READ_BUFFER_SIZE, MMAP_THRESHOLD, OPERATION_TIMEOUT, READ_BUFFER_SIZE,
+};
+pub use state::ScanState;
+pub use syslog::{facility_code, FACILITY_NAMES};
+pub use theme::{Theme, Themed};
+pub use timestamp::{format_timestamp, parse_line_timestamp};
+pub use wildcard::WildcardKeywords;
+
+/// Aggregate counters for a whole scan.
+#[derive(Debug, Default, Serialize)]
+pub struct ScanStats {
+    pub total_files: usize,
+    pub processed_files: usize,
+    pub total_errors: usize,
+    /// `total_errors` broken down by the level of each matching line
+    pub errors_by_level: LevelCounts,
+    pub skipped_files: usize,
+    pub large_files: usize,
+    pub truncated_files: usize,
+    /// Files that changed while they were being scanned
+    pub changed_files: usize,
+    pub excluded_paths: usize,
+    /// Files left out for being smaller than the minimum size
+    pub small_files: usize,
+    /// Files never scanned because the scan was stopped early
+    pub unscanned_files: usize,
+    /// Matches left out because the baseline suppresses them
+    pub suppressed_matches: usize,
+    /// Display paths of files skipped for exceeding the size limit
+    pub oversized_files: Vec<String>,
+    /// Display paths of files skipped for taking longer than the timeout
+    pub timed_out_files: Vec<String>,
+    /// Bytes read from the scanned files, compressed ones counting their
+    /// compressed size
+    pub bytes_read: u64,
+    /// Lines read from the scanned files
+    pub lines_read: usize,
+    /// Matches without a timestamp of their own
+    pub untimed_matches: usize,
+    /// Matches counted by the minute since the Unix epoch they fall in, when
+    /// [`ScanOptions::per_minute`] is set
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub matches_per_minute: BTreeMap<u64, usize>,
+    /// Every file of the scan and what became of it, in path order, when
+    /// [`ScanOptions::list_scanned`] is set
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub scanned_files: Vec<ScannedFile>,
+}
+
+/// What became of a file, as listed in [`ScanStats::scanned_files`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    Scanned,
+    /// Skipped for exceeding the size limit
+    Oversized,
+    /// Skipped for taking longer than the timeout
+    TimedOut,
+    /// Skipped because it couldn't be read
+    Failed,
+    /// Found not to be text when it was opened
+    NotText,
+    /// Never started because the scan was stopped early
+    Unscanned,
+    /// Listed to be scanned but not found
+    Missing,
+}
+
+/// A file of the scan with its size (compressed size for compressed files)
+/// and outcome.
+#[derive(Clone, Debug, Serialize)]
+pub struct ScannedFile {
+    pub path: String,
+    pub size: Option<u64>,
+    pub status: FileStatus,
+    /// Why the file failed, for [`FileStatus::Failed`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ScanStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Everything a scan produced.
+#[derive(Debug)]
+pub struct ScanReport {
+    /// Files with at least one match, keyed by their path relative to the scan root
+    pub errors_by_file: Vec<(String, FileReport)>,
+    pub stats: ScanStats,
+    /// Files that could not be scanned, other than those skipped for size
+    pub failures: Vec<(PathBuf, AppError)>,
+    pub duration: Duration,
+}
+
+// What became of one collected file
+enum FileOutcome {
+    Scanned(Result<FileReport>),
+    // Sniffed at scan time and found not to be text
+    NotText,
+    // Never started because the scan was stopped early
+    Unscanned,
+    // An archive, standing for the files in it
+    Archive(Vec<(PathBuf, FileOutcome)>),
+}
+
+// Folds per-file results into a report as they come in
+struct ReportBuilder<'a> {
+    root: &'a Path,
+    path_style: PathStyle,
+    list_scanned: bool,
+    stats: ScanStats,
+    failures: Vec<(PathBuf, AppError)>,
+    start_time: Instant,
+}
+
+impl<'a> ReportBuilder<'a> {
+    fn new(root: &'a Path, collected: &CollectedFiles, options: &ScanOptions) -> Self {
+        let mut stats = ScanStats::new();
+        stats.total_files = collected.files.len() + collected.missing.len();
+        stats.skipped_files = collected.missing.len();
+        stats.excluded_paths = collected.excluded;
+        stats.small_files = collected.too_small;
+
+        let mut builder = Self {
+            root,
+            path_style: options.path_style,
+            list_scanned: options.list_scanned,
+            stats,
+            failures: Vec::new(),
+            start_time: Instant::now(),
+        };
+        for path in &collected.missing {
+            builder.list(path, FileStatus::Missing, None, None);
+        }
+        builder
+    }
+
+    // Notes a file's outcome for --list-scanned; sizes not known from the
+    // scan are looked up, which fails for missing files and archive members
+    fn list(&mut self, file_path: &Path, status: FileStatus, size: Option<u64>, error: Option<String>) {
+        if !self.list_scanned {
+            return;
+        }
+        self.stats.scanned_files.push(ScannedFile {
+            path: self.path_style.display(file_path, self.root),
+            size: size.or_else(|| fs::metadata(file_path).ok().map(|metadata| metadata.len())),
+            status,
+            error,
+        });
+    }
+
+    // Returns the display path and report of each file with matches, which
+    // for an archive can be several
+    fn add(&mut self, file_path: &Path, outcome: FileOutcome) -> Vec<(String, FileReport)> {
+        match outcome {
+            FileOutcome::Scanned(result) => self.add_result(file_path, result).into_iter().collect(),
+            // Turned out not to be text, so it was never really part of the scan
+            FileOutcome::NotText => {
+                self.stats.total_files -= 1;
+                self.list(file_path, FileStatus::NotText, None, None);
+                Vec::new()
+            }
+            FileOutcome::Unscanned => {
+                self.stats.unscanned_files += 1;
+                self.list(file_path, FileStatus::Unscanned, None, None);
+                Vec::new()
+            }
+            FileOutcome::Archive(members) => {
+                // The totals count the members instead of the archive
+                self.stats.total_files += members.len();
+                self.stats.total_files -= 1;
+                members.into_iter()
+                    .flat_map(|(member_path, outcome)| self.add(&member_path, outcome))
+                    .collect()
+            }
+        }
+    }
+
+    fn add_result(&mut self, file_path: &Path, result: Result<FileReport>) -> Option<(String, FileReport)> {
+        let display_path = self.path_style.display(file_path, self.root);
+
+        match result {
+            Ok(mut report) => {
+                report.relative_path = file_path.strip_prefix(self.root).unwrap_or(file_path).to_path_buf();
+                self.list(file_path, FileStatus::Scanned, Some(report.bytes_read), None);
+                self.stats.processed_files += 1;
+                self.stats.suppressed_matches += report.suppressed_count;
+                self.stats.bytes_read += report.bytes_read;
+                self.stats.lines_read += report.lines_read;
+                if report.changed_during_scan {
+                    self.stats.changed_files += 1;
+                }
+                if report.match_count > 0 {
+                    self.stats.total_errors += report.match_count;
+                    self.stats.untimed_matches += report.untimed_count;
+                    for (minute, count) in &report.matches_per_minute {
+                        *self.stats.matches_per_minute.entry(*minute).or_insert(0) += count;
+                    }
+                    self.stats.errors_by_level += report.matches_by_level;
+                    if report.is_truncated() {
+                        self.stats.truncated_files += 1;
+                    }
+                    return Some((display_path, report));
+                }
+            }
+            Err(AppError::FileSizeError(_)) => {
+                // Listed in the summary rather than reported one by one
+                self.list(file_path, FileStatus::Oversized, None, None);
+                self.stats.large_files += 1;
+                self.stats.skipped_files += 1;
+                self.stats.oversized_files.push(display_path);
+            }
+            Err(AppError::TimeoutError(_)) => {
+                // Unlike other failures these scan fine with a longer timeout
+                self.list(file_path, FileStatus::TimedOut, None, None);
+                self.stats.skipped_files += 1;
+                self.stats.timed_out_files.push(display_path);
+            }
+            Err(e) => {
+                self.list(file_path, FileStatus::Failed, None, Some(e.to_string()));
+                self.stats.skipped_files += 1;
+                self.failures.push((file_path.to_path_buf(), e));
+            }
+        }
+        None
+    }
+
+    fn finish(mut self, errors_by_file: Vec<(String, FileReport)>) -> ScanReport {
+        // Files finish in whatever order the workers get to them
+        self.stats.scanned_files.sort_by(|a, b| natural_cmp(&a.path, &b.path));
+        ScanReport {
+            errors_by_file,
+            stats: self.stats,
+            failures: self.failures,
+            duration: self.start_time.elapsed(),
+        }
+    }
+}
+
+// Files whose content wasn't checked during collection are sniffed on the
+// handle that is then scanned
+fn scan_collected_file(file_path: &Path, collected: &CollectedFiles, options: &ScanOptions) -> FileOutcome {
+    let checkpoint = options.checkpoint.as_deref();
+    if let Some(outcome) = checkpoint.and_then(|checkpoint| checkpoint.finished(file_path)) {
+        return outcome;
+    }
+
+    let outcome = if options.should_stop() {
+        FileOutcome::Unscanned
+    } else if collected.from_journal {
+        FileOutcome::Scanned(journal::scan_journal(options))
+    } else if is_archive(file_path) {
+        match archive::scan_archive(file_path, options) {
+            Ok(members) => FileOutcome::Archive(members.into_iter()
+                .map(|(member_path, result)| {
+                    let outcome = result.transpose().map_or(FileOutcome::NotText, FileOutcome::Scanned);
+                    log_outcome(options, &member_path, &outcome);
+                    (member_path, outcome)
+                })
+                .collect()),
+            Err(e) => FileOutcome::Scanned(Err(e)),
+        }
+    } else if collected.unsniffed.contains(file_path) {
+        scanner::process_unsniffed_file(file_path, options)
+            .transpose()
+            .map_or(FileOutcome::NotText, FileOutcome::Scanned)
+    } else {
+        FileOutcome::Scanned(process_log_file(file_path, options))
+    };
+
+    log_outcome(options, file_path, &outcome);
+    if let Some(checkpoint) = checkpoint {
+        checkpoint.record(file_path, &outcome);
+    }
+    outcome
+}
+
+fn log_outcome(options: &ScanOptions, file_path: &Path, outcome: &FileOutcome) {
+    match outcome {
+        FileOutcome::NotText => log_skipped(options, file_path, "binary content"),
+        FileOutcome::Scanned(Err(AppError::FileSizeError(_))) => log_skipped(options, file_path,
+            &format!("larger than the size limit of {}", format_size(options.max_size, BINARY))),
+        FileOutcome::Scanned(Err(AppError::TimeoutError(_))) => log_skipped(options, file_path,
+            &format!("took longer than the timeout of {}s", options.timeout.unwrap_or_default().as_secs())),
+        _ => {}
+    }
+}
+
+/// Scans previously collected files in parallel.
+///
+/// `on_file_done` is called from the worker threads after each file, which
+/// makes it a good place to drive a progress bar.
+pub fn scan_files<F>(root: &Path, collected: &CollectedFiles, options: &ScanOptions, on_file_done: F) -> ScanReport
+where
+    F: Fn(&Path) + Sync,
+{
+    let mut builder = ReportBuilder::new(root, collected, options);
+
+    // Process files in parallel
+    let results: Vec<_> = collected.files.par_iter()
+        .map(|file_path| {
+            let result = scan_collected_file(file_path, collected, options);
+            on_file_done(file_path);
+            (file_path, result)
+        })
+        .collect();
+
+    let errors_by_file = results.into_iter()
+        .flat_map(|(file_path, result)| builder.add(file_path, result))
+        .collect();
+
+    builder.finish(errors_by_file)
+}
+
+/// Scans previously collected files in parallel, handing each file's matches
+/// to `on_report` as soon as that file is done.
+///
+/// Reports arrive in completion order rather than path order and are not
+/// kept, so the returned [`ScanReport`] has empty `errors_by_file`; memory use
+/// stays bounded by the files being scanned at any one time. `on_report`
+/// runs on the calling thread, `on_file_done` on the worker threads.
+pub fn scan_files_streaming<F, R>(root: &Path, collected: &CollectedFiles, options: &ScanOptions, on_file_done: F, mut on_report: R) -> ScanReport
+where
+    F: Fn(&Path) + Sync,
+    R: FnMut(String, FileReport),
+{
+    let mut builder = ReportBuilder::new(root, collected, options);
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            collected.files.par_iter().for_each_with(sender, |sender, file_path| {
+                let result = scan_collected_file(file_path, collected, options);
+                on_file_done(file_path);
+                // The receiver only goes away once every file has been sent
+                let _ = sender.send((file_path, result));
+            });
+        });
+
+        for (file_path, result) in receiver {
+            for (display_path, report) in builder.add(file_path, result) {
+                on_report(display_path, report);
+            }
+        }
+    });
+
+    builder.finish(Vec::new())
+}
+
+/// Collects the text files under `path` and scans them for matches.
+pub fn scan_directory(path: &Path, options: &ScanOptions) -> Result<ScanReport> {
+    if !path.exists() {
+        return Err(AppError::IoError(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Directory {} does not exist", path.display())
+        )));
+    }
+
+    let collected = collect_files(path, options)?;
+    if collected.files.is_empty() {
+        return Err(AppError::IoError(io::Error::other("No readable files found")));
+    }
+
+    Ok(scan_files(path, &collected, options, |_| {}))
+}
@@ -1,218 +1,1597 @@
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::fs;
-use std::io::{self, BufRead, Write, Read};
-use std::error::Error;
-use std::path::{Path, PathBuf};
-use std::fmt;
-use std::time::{SystemTime, Duration, Instant};
-use chrono::{DateTime, Local};
+use std::io::{self, IsTerminal, Write};
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
 use colored::*;
-use indicatif::{ProgressBar, ProgressStyle};
 use humansize::{format_size, BINARY};
-use rayon::prelude::*;
-
-// Custom error type for the application
-#[derive(Debug)]
-enum AppError {
-    IoError(io::Error),
-    PermissionDenied(String),
-    InvalidInput(String),
-    FileProcessingError { path: PathBuf, error: String },
-    FileSizeError(String),
-    TimeoutError(String),
-    EncodingError(String),
-}
-
-impl fmt::Display for AppError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            AppError::IoError(err) => write!(f, "IO error: {}", err),
-            AppError::PermissionDenied(msg) => write!(f, "Permission denied: {}", msg),
-            AppError::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
-            AppError::FileProcessingError { path, error } => {
-                write!(f, "Error processing file {:?}: {}", path, error)
-            },
-            AppError::FileSizeError(msg) => write!(f, "File size error: {}", msg),
-            AppError::TimeoutError(msg) => write!(f, "Operation timed out: {}", msg),
-            AppError::EncodingError(msg) => write!(f, "Encoding error: {}", msg),
+use encoding_rs::Encoding;
+use indicatif::{ProgressBar, ProgressStyle};
+use clap::{CommandFactory, FromArgMatches, Parser, ValueEnum};
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use serde::Serialize;
+use rustwatch::{
+    build_exclude_set, collect_files_from, collect_glob_files, collect_journal, collect_listed_files, facility_code, follow_reader, journal_available,
+    natural_cmp, normalize_message, scan_files, scan_files_streaming,
+    AppError, Baseline, BytePattern, Checkpoint, CollectedFiles, ContinuationStyle, FileReport, FileStatus, Follower, JsonFieldMatch, LevelCounts, LogEntry, PathStyle, Result,
+    ScanOptions, ScanState, ScanStats, ScannedFile, Severity, SniffSettings, Theme, Themed, WildcardKeywords,
+    DEFAULT_RETRIES, FACILITY_NAMES, MAX_FILE_SIZE, MAX_LINE_LENGTH, MAX_NON_ASCII_RATIO, MAX_NULL_RATIO, OPERATION_TIMEOUT,
+    SNIFF_LEN,
+};
+
+mod align;
+mod compare;
+mod config;
+mod html;
+mod markdown;
+mod metrics;
+mod notify;
+mod split;
+
+use align::align_columns;
+use compare::{Comparison, PreviousScan};
+use config::Config;
+use html::print_html_report;
+use markdown::print_markdown_report;
+use metrics::print_metrics;
+use notify::Notifier;
+use split::write_split_reports;
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+mod user_privileges {
+    use std::io;
+
+    pub const MISSING: &str = "This tool is not running with sudo privileges.";
+    pub const HINT: &str = "Run with sudo for full access.";
+
+    pub fn is_elevated() -> io::Result<bool> {
+        Ok(unsafe { libc::geteuid() == 0 })
+    }
+}
+
+#[cfg(windows)]
+mod user_privileges {
+    use std::io;
+    use std::mem;
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::Security::{GetTokenInformation, TokenElevation, TOKEN_ELEVATION, TOKEN_QUERY};
+    use windows_sys::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+
+    pub const MISSING: &str = "This tool is not running as Administrator.";
+    pub const HINT: &str = "Run as Administrator for full access.";
+
+    // Checks whether the process token is elevated (UAC "Run as Administrator")
+    pub fn is_elevated() -> io::Result<bool> {
+        unsafe {
+            let mut token: HANDLE = std::ptr::null_mut();
+            if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) == 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mut elevation = TOKEN_ELEVATION { TokenIsElevated: 0 };
+            let mut returned = 0;
+            let succeeded = GetTokenInformation(
+                token,
+                TokenElevation,
+                &mut elevation as *mut TOKEN_ELEVATION as *mut _,
+                mem::size_of::<TOKEN_ELEVATION>() as u32,
+                &mut returned,
+            );
+            CloseHandle(token);
+
+            if succeeded == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(elevation.TokenIsElevated != 0)
         }
     }
 }
 
-impl Error for AppError {}
+const LEVEL_NAMES: [&str; 7] = ["debug", "info", "warn", "warning", "error", "critical", "fatal"];
+const PATH_STYLES: [&str; 3] = ["relative", "absolute", "basename"];
+const THEMES: [&str; 3] = ["dark", "light", "mono"];
+// What matches read with --stdin-stream are labelled with
+const STDIN_NAME: &str = "stdin";
+const CONTINUATION_STYLES: [&str; 2] = ["untimed", "indented"];
+const EXIT_CLEAN: i32 = 0;
+const EXIT_MATCHES: i32 = 1;
+const EXIT_FAILURE: i32 = 2;
+const EXIT_INTERRUPTED: i32 = 130; // 128 + SIGINT, as shells report it
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Parser, Debug)]
+#[command(name = "rustwatch", version, about = "A lightning-fast, parallel log file error scanner")]
+struct Cli {
+    /// Directories to scan together, a single file, or glob patterns for the files to scan (e.g. '/var/log/**/*.log'), instead of --dir
+    #[arg(value_name = "PATH|GLOB", conflicts_with = "files_from")]
+    patterns: Vec<String>,
+
+    /// Directory, or single file, to scan (skips the interactive location prompt)
+    #[arg(short, long, value_name = "PATH")]
+    dir: Option<PathBuf>,
+
+    /// Scan the files listed in FILE, one per line, instead of walking a directory ("-" reads stdin)
+    #[arg(long, value_name = "FILE")]
+    files_from: Option<PathBuf>,
+
+    /// Scan the systemd journal through journalctl instead of log files (scans the files as usual where journalctl isn't available)
+    #[arg(long, conflicts_with_all = ["files_from", "follow", "incremental", "resume"])]
+    journald: bool,
+
+    /// Scan log lines piped to stdin as they arrive, printing each match right away (e.g. journalctl -f | rustwatch --stdin-stream); context lines are not shown
+    #[arg(long, conflicts_with_all = ["dir", "patterns", "files_from", "journald", "follow", "incremental", "resume", "dry_run",
+        "split_output", "summary_json", "metrics", "count", "compare", "first", "last", "top", "top_errors", "list_scanned", "rate", "stats"])]
+    stdin_stream: bool,
+
+    /// Start scanning without asking for confirmation
+    #[arg(short = 'y', long)]
+    yes: bool,
+
+    /// Read default options from this file instead of ./rustwatch.toml or ~/rustwatch.toml
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Keyword to search for (repeatable, lines matching any keyword are reported)
+    #[arg(short, long = "keyword", value_name = "WORD", default_value = "error")]
+    keywords: Vec<String>,
+
+    /// Match lines containing ALL of these terms instead of --keyword (with --any-of, a line needs all of these and one of those)
+    #[arg(long, value_name = "TERMS", value_delimiter = ',')]
+    all_of: Vec<String>,
+
+    /// Match lines containing ANY of these terms instead of --keyword (combines with --all-of as above)
+    #[arg(long, value_name = "TERMS", value_delimiter = ',')]
+    any_of: Vec<String>,
+
+    /// Match lines with a word fitting PATTERN instead of --keyword (repeatable, '*' matches any text and '?' one character, e.g. 'err*')
+    #[arg(long, value_name = "PATTERN", conflicts_with_all = ["all_of", "any_of"])]
+    glob_keyword: Vec<String>,
+
+    /// Match lines whose raw bytes contain this hex sequence instead of --keyword (repeatable, e.g. 0xDEADBEEF); lines are shown with bytes outside printable ASCII escaped as \xNN
+    #[arg(long, value_name = "HEX", conflicts_with_all = ["all_of", "any_of", "glob_keyword"])]
+    byte_pattern: Vec<BytePattern>,
+
+    /// Drop matched lines that also contain PATTERN (repeatable, '*' matches any text, as in --baseline)
+    #[arg(long, value_name = "PATTERN")]
+    exclude_line: Vec<String>,
+
+    /// Only match keywords that appear as whole words ("error" won't match "errors" or "no-error")
+    #[arg(short, long)]
+    word: bool,
+
+    /// Match keywords case-sensitively ("error" won't match "Error" or "ERROR")
+    #[arg(short = 's', long)]
+    case_sensitive: bool,
+
+    /// Collapse identical matching lines within a file into one entry with an occurrence count
+    #[arg(long)]
+    dedupe: bool,
+
+    /// Collapse matching lines that differ only in numbers, UUIDs, IPs or hex ids, showing one example per group
+    #[arg(long)]
+    group_similar: bool,
+
+    /// Report a matched line together with the lines continuing it, such as a stack trace, as one entry: 'untimed' takes lines without a timestamp, 'indented' lines starting with whitespace (not while following)
+    #[arg(long, value_name = "STYLE",
+        value_parser = PossibleValuesParser::new(CONTINUATION_STYLES).map(|style| style.parse::<ContinuationStyle>().unwrap()))]
+    merge_continuation: Option<ContinuationStyle>,
+
+    /// Report lines that do NOT match, like grep -v
+    #[arg(short = 'v', long)]
+    invert: bool,
+
+    /// Show N lines of context before and after each match
+    #[arg(short = 'C', long, value_name = "N", default_value_t = 0)]
+    context: usize,
+
+    /// Show N lines of context before each match, overriding --context
+    #[arg(short = 'B', long, value_name = "N")]
+    before_context: Option<usize>,
+
+    /// Show N lines of context after each match (e.g. a stack trace), overriding --context
+    #[arg(short = 'A', long, value_name = "N")]
+    after_context: Option<usize>,
+
+    /// Only report matches at or after this time (e.g. "2024-06-20 00:00:00" or "1h" for the last hour)
+    #[arg(long, value_name = "TIME", value_parser = parse_time_bound)]
+    since: Option<SystemTime>,
+
+    /// Only report matches at or before this time (absolute or relative, like --since)
+    #[arg(long, value_name = "TIME", value_parser = parse_time_bound)]
+    until: Option<SystemTime>,
+
+    /// Keep matches without a parseable timestamp when --since/--until is used
+    #[arg(long)]
+    include_untimed: bool,
+
+    /// Only print the number of matches per file instead of each matching line
+    #[arg(short = 'c', long)]
+    count: bool,
+
+    /// Stop collecting matches for a file after N hits (the file is still counted in full)
+    #[arg(short, long, value_name = "N")]
+    max_matches: Option<usize>,
+
+    /// Per-file processing timeout in seconds (0 disables the timeout)
+    #[arg(long, value_name = "SECS", default_value_t = OPERATION_TIMEOUT.as_secs())]
+    timeout: u64,
+
+    /// Retry opening a file up to N times on transient errors, e.g. on NFS or CIFS (each retry waits twice as long)
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_RETRIES)]
+    retries: u32,
+
+    /// Stop starting new files after this many seconds and report what was found so far
+    #[arg(long, value_name = "SECS")]
+    deadline: Option<u64>,
+
+    /// Character encoding of files without a byte order mark (e.g. latin1, windows-1252, utf-16le)
+    #[arg(long, value_name = "LABEL", value_parser = parse_encoding)]
+    encoding: Option<&'static Encoding>,
+
+    /// Skip files larger than this size (accepts suffixes like 500K, 50M or 4G)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, default_value_t = MAX_FILE_SIZE)]
+    max_size: u64,
+
+    /// Skip files smaller than this size, such as empty placeholders and lock files (same suffixes as --max-size)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, default_value_t = 0)]
+    min_size: u64,
+
+    /// Cut lines longer than this and mark them as truncated (accepts suffixes like 64K or 4M)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, default_value_t = MAX_LINE_LENGTH as u64)]
+    max_line_length: u64,
+
+    /// Read files through a buffer of this size (e.g. 16K) instead of one sized to each file, up to 128K
+    #[arg(long, value_name = "SIZE", value_parser = parse_buffer_size)]
+    buffer_size: Option<usize>,
+
+    /// Memory-map plain files over 64MB instead of reading them through a buffer (faster on large logs)
+    #[arg(long)]
+    mmap: bool,
+
+    /// Skip files and directories matching this glob (repeatable, e.g. '*.gz' or 'journal/*')
+    #[arg(short = 'x', long = "exclude", value_name = "GLOB")]
+    excludes: Vec<String>,
+
+    /// Descend at most N directory levels below the scan directory (0 scans only its own files)
+    #[arg(long, value_name = "N")]
+    max_depth: Option<usize>,
+
+    /// Scan only the files directly in the scan directory, the same as --max-depth 0
+    #[arg(long, conflicts_with = "max_depth")]
+    no_recursive: bool,
+
+    /// Only scan files modified within this long (e.g. 15m, 2h, 1d)
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    modified_within: Option<Duration>,
+
+    /// Stop collecting files after finding N, to guard against pointing at a huge tree (files that turn out to be binary still count)
+    #[arg(long, value_name = "N")]
+    max_files: Option<usize>,
+
+    /// Descend into symlinked directories (directories reached twice are still skipped)
+    #[arg(long)]
+    follow_symlinks: bool,
+
+    /// Also treat files with these extensions as text without sniffing their content (e.g. trace,jsonl)
+    #[arg(long, value_name = "EXT", value_delimiter = ',')]
+    extensions: Vec<String>,
+
+    /// Scan every file found, not just the ones that look like text
+    #[arg(long)]
+    all_files: bool,
+
+    /// How many bytes from the start of a file without a known extension are checked to tell text from binary
+    #[arg(long, value_name = "N", default_value_t = SNIFF_LEN as u64, value_parser = clap::value_parser!(u64).range(1..))]
+    sniff_bytes: u64,
+
+    /// Checked bytes with at least this share of null bytes make a file binary
+    #[arg(long, value_name = "RATIO", default_value_t = MAX_NULL_RATIO, value_parser = parse_ratio)]
+    max_null_ratio: f32,
+
+    /// Checked bytes with at least this share of non-ASCII bytes make a file binary (raise it for non-Latin text)
+    #[arg(long, value_name = "RATIO", default_value_t = MAX_NON_ASCII_RATIO, value_parser = parse_ratio)]
+    max_nonascii_ratio: f32,
+
+    /// Don't report matches containing any pattern in FILE, one per line ('*' matches any text)
+    #[arg(long, value_name = "FILE")]
+    baseline: Option<PathBuf>,
+
+    /// Scan files matched by .gitignore, .ignore and global git excludes too
+    #[arg(long)]
+    no_ignore: bool,
+
+    /// Also report lines whose log level is at least this severe, even without a keyword match
+    #[arg(long, value_name = "LEVEL", value_parser = PossibleValuesParser::new(LEVEL_NAMES).map(|level| level.parse::<Severity>().unwrap()))]
+    min_level: Option<Severity>,
+
+    /// Read the level of lines with a syslog <PRI> prefix from the priority; with --min-level they match by priority alone
+    #[arg(long)]
+    syslog: bool,
+
+    /// With --syslog, only report prefixed lines from these facilities (e.g. auth,daemon,local0)
+    #[arg(long, value_name = "NAME", value_delimiter = ',', requires = "syslog",
+        value_parser = PossibleValuesParser::new(FACILITY_NAMES).map(|name| facility_code(&name).unwrap()))]
+    facility: Vec<u8>,
+
+    /// Match JSON log lines on a field's value instead of by keyword (e.g. level=error; nested fields with dots); other lines still match by keyword
+    #[arg(long, value_name = "FIELD=VALUE")]
+    json_field: Vec<JsonFieldMatch>,
+
+    /// Show this field of matched JSON lines (e.g. message) instead of the whole line
+    #[arg(long, value_name = "FIELD")]
+    json_display: Option<String>,
+
+    /// Number of worker threads used for scanning (defaults to one per CPU core)
+    #[arg(long, value_name = "N")]
+    threads: Option<NonZeroUsize>,
+
+    /// Order of files in the report (the default can be set with RUSTWATCH_SORT)
+    #[arg(long, value_enum, env = "RUSTWATCH_SORT", default_value_t = SortOrder::Path)]
+    sort: SortOrder,
+
+    /// What the progress bar counts
+    #[arg(long, value_enum, value_name = "UNIT", default_value_t = ProgressUnit::Files)]
+    progress: ProgressUnit,
+
+    /// Explain on stderr why files were skipped; give it twice to also list each directory entered
+    #[arg(long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Show at most N characters of each line in the console, keeping the match in view (other formats keep whole lines)
+    #[arg(long, value_name = "N")]
+    truncate: Option<usize>,
+
+    /// Line up the timestamp, level and message of matched lines in columns, file by file
+    #[arg(long)]
+    align: bool,
+
+    /// How to show file paths: relative to the scanned directory, absolute, or only the file name
+    #[arg(long, value_name = "STYLE", default_value = "relative",
+        value_parser = PossibleValuesParser::new(PATH_STYLES).map(|style| style.parse::<PathStyle>().unwrap()))]
+    path_style: PathStyle,
+
+    /// Print only the summary (nothing at all with --format json or csv); warnings still go to stderr
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Print nothing at all when no matches are found, and the full report otherwise (for cron, where silence means success)
+    #[arg(long, conflicts_with = "follow")]
+    quiet_if_clean: bool,
+
+    /// Exit with status 1 when any matches are found (failures always exit with 2)
+    #[arg(long)]
+    exit_on_match: bool,
+
+    /// Exit with status 1 only when more than N matches are found
+    #[arg(long, value_name = "N", conflicts_with = "exit_on_match")]
+    fail_threshold: Option<usize>,
+
+    /// List the files that would be scanned, with their sizes, and exit without scanning
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Print each file's matches as soon as it is scanned (file order is not preserved, --sort is ignored)
+    #[arg(long, conflicts_with = "count")]
+    stream: bool,
+
+    /// Print only the scan statistics as a single JSON object
+    #[arg(long, conflicts_with_all = ["format", "quiet", "count", "stream", "top", "follow"])]
+    summary_json: bool,
+
+    /// Print the results as Prometheus metrics (e.g. for node_exporter's textfile collector)
+    #[arg(long, conflicts_with_all = ["format", "quiet", "count", "stream", "top", "follow", "summary_json"])]
+    metrics: bool,
+
+    /// Also list the N most frequent messages across all files (similar lines are grouped)
+    #[arg(long, value_name = "N", conflicts_with_all = ["stream", "summary_json", "metrics"])]
+    top_errors: Option<usize>,
+
+    /// Also list the matches per directory, most first, to see which part of the tree is noisiest
+    #[arg(long, conflicts_with_all = ["stream", "summary_json", "metrics"])]
+    group_by_dir: bool,
+
+    /// With --group-by-dir, roll directories up to their first N levels (e.g. 1 for the top-level directories)
+    #[arg(long, value_name = "N", requires = "group_by_dir")]
+    dir_depth: Option<usize>,
+
+    /// Add throughput to the summary: bytes and lines read, their rates, and where the time went
+    #[arg(long, conflicts_with_all = ["summary_json", "metrics"])]
+    stats: bool,
+
+    /// After the summary, list every file with its size and whether it was scanned, skipped or timed out (scanned_files in JSON)
+    #[arg(long)]
+    list_scanned: bool,
+
+    /// Write each file's matches to its own report in DIR (.txt, or .json with --format json), with an index, instead of printing them
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["stream", "summary_json", "metrics", "count", "compare", "output"])]
+    split_output: Option<PathBuf>,
+
+    /// Add the average and peak number of matches per minute, going by their timestamps
+    #[arg(long, conflicts_with_all = ["summary_json", "metrics"])]
+    rate: bool,
+
+    /// Show only what changed since an earlier --format json report of the same directory
+    #[arg(long, value_name = "REPORT", conflicts_with_all = ["count", "stream", "top", "follow", "summary_json", "metrics"])]
+    compare: Option<PathBuf>,
+
+    /// Only show the N earliest matches across all files, going by their timestamps
+    #[arg(long, value_name = "N", conflicts_with_all = ["count", "stream", "top", "compare", "split_output", "summary_json", "metrics"])]
+    first: Option<usize>,
+
+    /// Only show the N latest matches across all files, going by their timestamps (combines with --first)
+    #[arg(long, value_name = "N", conflicts_with_all = ["count", "stream", "top", "compare", "split_output", "summary_json", "metrics"])]
+    last: Option<usize>,
+
+    /// Only show the N files with the most matches (totals still cover every file)
+    #[arg(long, value_name = "N", conflicts_with = "stream")]
+    top: Option<usize>,
+
+    /// Output format for the scan results
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Disable colored output (also off when stdout is not a terminal or NO_COLOR is set)
+    #[arg(long)]
+    no_color: bool,
+
+    /// Color palette: dark or light for the terminal's background, or mono for only bold and dim text
+    #[arg(long, value_name = "THEME", env = "RUSTWATCH_THEME", default_value = "dark",
+        value_parser = PossibleValuesParser::new(THEMES).map(|theme| theme.parse::<Theme>().unwrap()))]
+    theme: Theme,
+
+    /// Write the report to a file instead of stdout (colors are disabled)
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Append to the output file instead of truncating it
+    #[arg(long, requires = "output")]
+    append: bool,
+
+    /// After the scan, keep watching the files and report new matches as they are appended (numbering new lines from 1)
+    #[arg(long)]
+    follow: bool,
+
+    /// Show a desktop notification for new matches found while following
+    #[arg(long, requires = "follow")]
+    notify: bool,
+
+    /// Only scan what was appended since the last --incremental run (rotated files are rescanned)
+    #[arg(long)]
+    incremental: bool,
+
+    /// Where --incremental remembers how far each file was scanned (defaults to the user cache directory)
+    #[arg(long, value_name = "FILE", requires = "incremental")]
+    state_file: Option<PathBuf>,
+
+    /// Record finished files in FILE as the scan goes, and skip those already recorded there (their results are kept)
+    #[arg(long, value_name = "FILE")]
+    resume: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    /// Colored, human-readable console output
+    Text,
+    /// A single JSON document on stdout
+    Json,
+    /// One CSV row per matched line
+    Csv,
+    /// A self-contained HTML page with collapsible sections per file
+    Html,
+    /// A Markdown document for pasting into tickets
+    Markdown,
+    /// One JSON object per matched line, printed as each file is scanned
+    Ndjson,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ProgressUnit {
+    /// Advance by one for each file scanned
+    Files,
+    /// Advance by each file's size, for an ETA that holds up when a few files dominate
+    Bytes,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum SortOrder {
+    /// Alphabetically by path, with numbers compared by value
+    Path,
+    /// Files with the most matches first
+    Count,
+}
+
+// Accepts durations such as "90s", "15m", "2h", "1d" or "1w"
+fn parse_duration(value: &str) -> std::result::Result<Duration, String> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount.parse()
+        .map_err(|_| format!("invalid duration '{}'", value))?;
+
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("invalid duration unit in '{}' (use s, m, h, d or w)", value)),
+    };
+    Ok(Duration::from_secs(amount * seconds))
+}
+
+// Accepts a byte count with an optional binary suffix, such as "4096", "500K", "50M" or "4G"
+fn parse_size(value: &str) -> std::result::Result<u64, String> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount.parse()
+        .map_err(|_| format!("invalid size '{}'", value))?;
 
-impl From<io::Error> for AppError {
-    fn from(err: io::Error) -> Self {
-        AppError::IoError(err)
+    let multiplier: u64 = match unit.to_uppercase().trim_end_matches("IB").trim_end_matches('B') {
+        "" => 1,
+        "K" => 1024,
+        "M" => 1024 * 1024,
+        "G" => 1024 * 1024 * 1024,
+        "T" => 1024 * 1024 * 1024 * 1024,
+        _ => return Err(format!("invalid size unit in '{}' (use K, M, G or T)", value)),
+    };
+    amount.checked_mul(multiplier)
+        .ok_or_else(|| format!("size '{}' is too large", value))
+}
+
+fn parse_buffer_size(value: &str) -> std::result::Result<usize, String> {
+    let size = parse_size(value)?;
+    if size == 0 {
+        return Err("the buffer needs to hold at least one byte".to_string());
+    }
+    usize::try_from(size).map_err(|_| format!("size '{}' is too large", value))
+}
+
+// A share between 0 and 1, such as 0.3
+fn parse_ratio(value: &str) -> std::result::Result<f32, String> {
+    value.trim().parse::<f32>().ok()
+        .filter(|ratio| (0.0..=1.0).contains(ratio))
+        .ok_or_else(|| format!("expected a ratio between 0 and 1, got '{}'", value))
+}
+
+fn parse_encoding(value: &str) -> std::result::Result<&'static Encoding, String> {
+    Encoding::for_label(value.trim().as_bytes())
+        .ok_or_else(|| format!("unknown encoding '{}'", value))
+}
+
+// Accepts an absolute local datetime or a duration relative to now
+fn parse_time_bound(value: &str) -> std::result::Result<SystemTime, String> {
+    if let Ok(duration) = parse_duration(value) {
+        return Ok(SystemTime::now() - duration);
+    }
+
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Ok(datetime.into());
+    }
+
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0)))
+        .ok_or_else(|| format!("invalid time '{}' (expected e.g. \"2024-06-20 00:00:00\" or \"1h\")", value))?;
+
+    Local.from_local_datetime(&naive)
+        .earliest()
+        .map(SystemTime::from)
+        .ok_or_else(|| format!("time '{}' does not exist in the local timezone", value))
+}
+
+// Where --incremental keeps its state unless --state-file says otherwise
+#[cfg(windows)]
+fn default_state_file() -> Option<PathBuf> {
+    std::env::var_os("LOCALAPPDATA").map(|dir| PathBuf::from(dir).join("rustwatch").join("state.json"))
+}
+
+#[cfg(target_os = "macos")]
+fn default_state_file() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Caches/rustwatch/state.json"))
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn default_state_file() -> Option<PathBuf> {
+    let cache_dir = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(cache_dir.join("rustwatch").join("state.json"))
+}
+
+fn state_file_path(cli: &Cli) -> Result<PathBuf> {
+    cli.state_file.clone()
+        .or_else(default_state_file)
+        .ok_or_else(|| AppError::InvalidInput(
+            "Cannot find a cache directory for the --incremental state; pass --state-file".to_string()
+        ))
+}
+
+// A state file that can't be read only costs one full rescan, so it isn't fatal
+fn load_scan_state(path: &Path) -> ScanState {
+    ScanState::load(path).unwrap_or_else(|e| {
+        eprintln!("{} Starting incremental state over: {}", "⚠️".warn(), e.to_string().warn());
+        ScanState::new()
+    })
+}
+
+fn build_scan_options(cli: &Cli) -> Result<ScanOptions> {
+    Ok(ScanOptions {
+        all_of: cli.all_of.clone(),
+        any_of: cli.any_of.clone(),
+        byte_patterns: cli.byte_pattern.clone(),
+        wildcard_keywords: (!cli.glob_keyword.is_empty())
+            .then(|| WildcardKeywords::new(&cli.glob_keyword, cli.case_sensitive))
+            .transpose()?,
+        case_sensitive: cli.case_sensitive,
+        whole_word: cli.word,
+        invert: cli.invert,
+        before_context: cli.before_context.unwrap_or(cli.context),
+        after_context: cli.after_context.unwrap_or(cli.context),
+        since: cli.since,
+        until: cli.until,
+        include_untimed: cli.include_untimed,
+        max_matches: cli.max_matches,
+        timeout: (cli.timeout > 0).then(|| Duration::from_secs(cli.timeout)),
+        retries: cli.retries,
+        deadline: cli.deadline.map(|secs| Instant::now() + Duration::from_secs(secs)),
+        max_size: cli.max_size,
+        min_size: cli.min_size,
+        min_level: cli.min_level,
+        json_fields: cli.json_field.clone(),
+        json_display: cli.json_display.clone(),
+        syslog: cli.syslog,
+        facilities: cli.facility.clone(),
+        excludes: build_exclude_set(&cli.excludes)?,
+        max_depth: if cli.no_recursive { Some(0) } else { cli.max_depth },
+        max_files: cli.max_files,
+        modified_after: cli.modified_within.map(|within| SystemTime::now() - within),
+        follow_symlinks: cli.follow_symlinks,
+        respect_ignore_files: !cli.no_ignore,
+        extra_extensions: cli.extensions.iter()
+            .map(|ext| ext.trim_start_matches('.').to_lowercase())
+            .collect(),
+        all_files: cli.all_files,
+        sniff: SniffSettings {
+            bytes: usize::try_from(cli.sniff_bytes).unwrap_or(usize::MAX),
+            max_null_ratio: cli.max_null_ratio,
+            max_non_ascii_ratio: cli.max_nonascii_ratio,
+        },
+        verbosity: cli.verbose,
+        encoding: cli.encoding,
+        buffer_size: cli.buffer_size,
+        use_mmap: cli.mmap,
+        baseline: cli.baseline.as_deref().map(Baseline::load).transpose()?,
+        excluded_lines: (!cli.exclude_line.is_empty()).then(|| Baseline::new(&cli.exclude_line)),
+        max_line_length: usize::try_from(cli.max_line_length).unwrap_or(usize::MAX),
+        per_minute: cli.rate,
+        list_scanned: cli.list_scanned,
+        dedupe: cli.dedupe,
+        group_similar: cli.group_similar,
+        merge_continuation: cli.merge_continuation,
+        path_style: cli.path_style,
+        incremental: cli.incremental
+            .then(|| state_file_path(cli).map(|path| Arc::new(load_scan_state(&path))))
+            .transpose()?,
+        checkpoint: cli.resume.as_deref().map(Checkpoint::load).transpose()?.map(Arc::new),
+        ..ScanOptions::new(cli.keywords.clone())
+    })
+}
+
+fn colorize_by_severity(text: &str, severity: Option<Severity>) -> ColoredString {
+    match severity {
+        Some(Severity::Critical) => text.critical().bold(),
+        Some(Severity::Warn) => text.warn(),
+        _ => text.bad(),
+    }
+}
+
+// How matched lines are drawn in the console
+struct ContentStyle<'a> {
+    options: &'a ScanOptions,
+    // --truncate: show at most this many characters of each line
+    truncate: Option<usize>,
+    // --align: line up the timestamp, level and message of each file's matches
+    align: bool,
+}
+
+impl ContentStyle<'_> {
+    // Context lines are cut from the start, as they have no match to keep in view
+    fn clip<'l>(&self, line: &'l str) -> Cow<'l, str> {
+        match self.truncate {
+            Some(max_chars) => {
+                let window = display_window(line, None, max_chars);
+                if window.end < line.len() {
+                    Cow::Owned(format!("{}…", &line[window]))
+                } else {
+                    Cow::Borrowed(line)
+                }
+            }
+            None => Cow::Borrowed(line),
+        }
+    }
+}
+
+// The byte range of `content` to show when lines are cut to `max_chars`
+// characters, shifted right if needed so the first match stays in view
+fn display_window(content: &str, first_match: Option<&Range<usize>>, max_chars: usize) -> Range<usize> {
+    let char_starts: Vec<usize> = content.char_indices().map(|(i, _)| i).collect();
+    if char_starts.len() <= max_chars {
+        return 0..content.len();
+    }
+    let byte_at = |char_index: usize| char_starts.get(char_index).copied().unwrap_or(content.len());
+
+    let mut start = 0;
+    if let Some(first_match) = first_match {
+        let match_start = char_starts.partition_point(|&i| i < first_match.start);
+        let match_end = char_starts.partition_point(|&i| i < first_match.end);
+        if match_end > max_chars {
+            // Keep a little of what leads up to the match
+            start = match_start.saturating_sub(max_chars / 4).min(char_starts.len() - max_chars);
+        }
+    }
+    byte_at(start)..byte_at(start + max_chars)
+}
+
+// Colors a matched line by its severity, with the keyword occurrences
+// underlined so it's clear what matched
+fn colorize_content(content: &str, severity: Option<Severity>, style: &ContentStyle) -> String {
+    let ranges = style.options.keyword_ranges(content);
+    let window = style.truncate.map_or(0..content.len(), |max_chars| display_window(content, ranges.first(), max_chars));
+
+    let mut colored = String::with_capacity(window.len() + 32);
+    if window.start > 0 {
+        colored.push_str(&"…".dimmed().to_string());
+    }
+    let mut last = window.start;
+    for range in ranges {
+        let range = range.start.max(window.start)..range.end.min(window.end);
+        if range.is_empty() {
+            continue;
+        }
+        if range.start > last {
+            colored.push_str(&colorize_by_severity(&content[last..range.start], severity).to_string());
+        }
+        colored.push_str(&colorize_by_severity(&content[range.clone()], severity).bold().underline().to_string());
+        last = range.end;
+    }
+    if last < window.end {
+        colored.push_str(&colorize_by_severity(&content[last..window.end], severity).to_string());
+    }
+    if window.end < content.len() {
+        colored.push_str(&"…".dimmed().to_string());
+    }
+    colored
+}
+
+// What a reported line is called in the console output, which changes with
+// --invert and --group-similar
+#[derive(Clone, Copy)]
+struct MatchLabels {
+    one: &'static str,
+    many: &'static str,
+    title: &'static str,
+    repeats: &'static str,
+}
+
+impl MatchLabels {
+    fn new(inverted: bool, grouped_similar: bool) -> Self {
+        let repeats = if grouped_similar { " similar" } else { "" };
+        if inverted {
+            Self { one: "non-matching line", many: "non-matching lines", title: "Non-matching Lines", repeats }
+        } else {
+            Self { one: "error", many: "errors", title: "Errors", repeats }
+        }
+    }
+
+    fn count(&self, count: usize) -> &'static str {
+        if count == 1 { self.one } else { self.many }
+    }
+}
+
+// e.g. "3 critical, 50 error, 400 warn", leaving out the levels not seen
+fn level_breakdown(counts: &LevelCounts) -> String {
+    [
+        (counts.critical, "critical".critical().bold()),
+        (counts.error, "error".bad()),
+        (counts.warn, "warn".warn()),
+        (counts.info, "info".normal()),
+        (counts.debug, "debug".dimmed()),
+        (counts.unknown, "without a level".normal()),
+    ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, level)| format!("{} {}", count, level))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn print_summary(out: &mut dyn Write, stats: &ScanStats, duration: Duration, labels: MatchLabels) -> io::Result<()> {
+    writeln!(out, "\n{}", "📊 Scan Statistics:".info().bold())?;
+    writeln!(out, "├─ Scan time: {} ms", duration.as_millis().to_string().info())?;
+    writeln!(out, "├─ Total files scanned: {}", stats.processed_files.to_string().good())?;
+    writeln!(out, "├─ Total {} found: {}", labels.many, stats.total_errors.to_string().warn())?;
+    if stats.total_errors > 0 {
+        writeln!(out, "├─ By level: {}", level_breakdown(&stats.errors_by_level))?;
+    }
+    writeln!(out, "├─ Files skipped: {}", stats.skipped_files.to_string().warn())?;
+    if stats.excluded_paths > 0 {
+        writeln!(out, "├─ Paths excluded: {}", stats.excluded_paths.to_string().warn())?;
+    }
+    if stats.small_files > 0 {
+        writeln!(out, "├─ Files below --min-size: {}", stats.small_files.to_string().warn())?;
+    }
+    if stats.truncated_files > 0 {
+        writeln!(out, "├─ Files truncated by --max-matches: {}", stats.truncated_files.to_string().warn())?;
     }
+    if stats.changed_files > 0 {
+        writeln!(out, "├─ Changed during scan, possibly incomplete: {}", stats.changed_files.to_string().warn())?;
+    }
+    if stats.suppressed_matches > 0 {
+        writeln!(out, "├─ Suppressed by baseline: {}", stats.suppressed_matches.to_string().warn())?;
+    }
+    writeln!(out, "└─ Large files encountered: {}", stats.large_files.to_string().warn())?;
+
+    if !stats.oversized_files.is_empty() {
+        writeln!(out, "\n{}", "📦 Skipped for exceeding the size limit:".warn().bold())?;
+        for file_name in &stats.oversized_files {
+            writeln!(out, "  {} {}", "└─".warn(), file_name)?;
+        }
+    }
+    if !stats.timed_out_files.is_empty() {
+        let count = stats.timed_out_files.len();
+        writeln!(out, "\n{}", format!("⌛ {} {} timed out — rerun with --timeout 0 to include them:",
+            count,
+            if count == 1 { "file" } else { "files" }).warn().bold())?;
+        for file_name in &stats.timed_out_files {
+            writeln!(out, "  {} {}", "└─".warn(), file_name)?;
+        }
+    }
+    Ok(())
 }
 
-const MAX_FILE_SIZE: u64 = 1024 * 1024 * 1024; // 1GB
-const OPERATION_TIMEOUT: Duration = Duration::from_secs(30);
-const TEXT_FILE_EXTENSIONS: &[&str] = &[
-    "log", "txt", "text", "err", "out", "output", "debug", 
-    "conf", "config", "cfg", "ini", "properties",
-    "yml", "yaml", "json", "xml", "env",
-    "md", "rst", "info"
-];
+fn print_threshold_exceeded(out: &mut dyn Write, total: usize, threshold: usize, labels: MatchLabels) -> io::Result<()> {
+    writeln!(out, "{} {}",
+        "🚨".bad(),
+        format!("{} {} found, {} over the --fail-threshold of {}",
+            total,
+            if total == 1 { labels.one } else { labels.many },
+            total - threshold,
+            threshold).bad().bold())
+}
 
-fn validate_file_size(size: u64, path: &Path) -> Result<()> {
-    if size > MAX_FILE_SIZE {
-        return Err(AppError::FileSizeError(
-            format!("File {:?} exceeds maximum size limit of {}", 
-                path, format_size(MAX_FILE_SIZE, BINARY))
-        ));
+fn print_scanned_files(out: &mut dyn Write, files: &[ScannedFile]) -> io::Result<()> {
+    writeln!(out, "\n{}", "📋 Files and what became of them:".info().bold())?;
+    for file in files {
+        let size = file.size.map_or_else(|| "size unknown".to_string(), |size| format_size(size, BINARY));
+        let status = match file.status {
+            FileStatus::Scanned => "scanned".good(),
+            FileStatus::Oversized => "skipped, over the size limit".warn(),
+            FileStatus::TimedOut => "skipped, timed out".warn(),
+            FileStatus::Failed => "skipped, unreadable".bad(),
+            FileStatus::NotText => "skipped, not text".warn(),
+            FileStatus::Unscanned => "not scanned, stopped early".warn(),
+            FileStatus::Missing => "not found".bad(),
+        };
+        write!(out, "  {} {} ({}): {}", "└─".info(), file.path, size, status)?;
+        match &file.error {
+            Some(error) => writeln!(out, " - {}", error.dimmed())?,
+            None => writeln!(out)?,
+        }
     }
     Ok(())
 }
 
-#[cfg(target_os = "linux")]
-mod user_privileges {
-    use std::io;
-    pub fn is_root_user() -> io::Result<bool> {
-        Ok(unsafe { libc::geteuid() == 0 })
+// Above this share of matches without a timestamp, --rate warns that it is skewed
+const UNTIMED_WARNING_SHARE: f64 = 0.2;
+
+fn print_rate(out: &mut dyn Write, stats: &ScanStats, labels: MatchLabels) -> io::Result<()> {
+    writeln!(out, "\n{}", "⏱️ Rate:".info().bold())?;
+    let per_minute = &stats.matches_per_minute;
+    let (Some((&first, _)), Some((&last, _))) = (per_minute.first_key_value(), per_minute.last_key_value()) else {
+        writeln!(out, "└─ No timestamped {} to measure", labels.many)?;
+        return Ok(());
+    };
+
+    let timed: usize = per_minute.values().sum();
+    let minutes = last - first + 1;
+    let (peak_minute, peak_count) = per_minute.iter()
+        .max_by_key(|(minute, count)| (**count, Reverse(**minute)))
+        .map(|(minute, count)| (*minute, *count))
+        .unwrap_or_default();
+    let minute_start = |minute: u64| Local.timestamp_opt((minute * 60) as i64, 0)
+        .single()
+        .map_or_else(String::new, |time| time.format("%Y-%m-%d %H:%M").to_string());
+
+    writeln!(out, "├─ Span: {} to {} ({} {})",
+        minute_start(first),
+        minute_start(last),
+        minutes,
+        if minutes == 1 { "minute" } else { "minutes" })?;
+    writeln!(out, "├─ Average: {} {} per minute", format!("{:.2}", timed as f64 / minutes as f64).warn(), labels.many)?;
+    writeln!(out, "└─ Peak minute: {} with {}", minute_start(peak_minute), peak_count.to_string().warn())?;
+
+    if stats.total_errors > 0 {
+        let untimed_share = stats.untimed_matches as f64 / stats.total_errors as f64;
+        if untimed_share > UNTIMED_WARNING_SHARE {
+            writeln!(out, "{} {}",
+                "⚠️".warn(),
+                format!("{} of {} {} ({:.0}%) had no timestamp and are not counted, so the rate is skewed",
+                    stats.untimed_matches,
+                    stats.total_errors,
+                    labels.many,
+                    untimed_share * 100.0).warn())?;
+        }
     }
+    Ok(())
 }
 
-type Result<T> = std::result::Result<T, AppError>;
+// Where the time of a run went, for --stats
+struct Timings {
+    traversal: Duration,
+    scanning: Duration,
+    printing: Duration,
+}
 
-#[derive(Debug)]
-struct LogEntry {
-    line_number: usize,
-    content: String,
-    timestamp: Option<SystemTime>,
+fn print_throughput(out: &mut dyn Write, stats: &ScanStats, timings: &Timings) -> io::Result<()> {
+    // Rates over a scan too quick to time are left at zero
+    let seconds = timings.scanning.as_secs_f64();
+    let per_second = |amount: f64| if seconds > 0.0 { amount / seconds } else { 0.0 };
+
+    writeln!(out, "\n{}", "⚡ Throughput:".info().bold())?;
+    writeln!(out, "├─ Bytes read: {} ({}/s)",
+        format_size(stats.bytes_read, BINARY).info(),
+        format_size(per_second(stats.bytes_read as f64) as u64, BINARY).info())?;
+    writeln!(out, "├─ Lines read: {} ({:.0} lines/s)",
+        stats.lines_read.to_string().info(),
+        per_second(stats.lines_read as f64))?;
+    writeln!(out, "├─ Traversal: {} ms", timings.traversal.as_millis().to_string().info())?;
+    writeln!(out, "├─ Scanning: {} ms", timings.scanning.as_millis().to_string().info())?;
+    writeln!(out, "└─ Printing: {} ms", timings.printing.as_millis().to_string().info())
 }
 
-impl LogEntry {
-    fn format_timestamp(&self) -> String {
-        self.timestamp
-            .map(|ts| {
-                let datetime: DateTime<Local> = ts.into();
-                datetime.format("%Y-%m-%d %H:%M:%S").to_string()
-            })
-            .unwrap_or_else(|| "Unknown time".to_string())
+fn print_no_matches(out: &mut dyn Write, labels: MatchLabels) -> io::Result<()> {
+    writeln!(out, "\n{} {}", "✅".good(), format!("No {} found in processed files.", labels.many).good())
+}
+
+fn print_errors_heading(out: &mut dyn Write, labels: MatchLabels) -> io::Result<()> {
+    writeln!(out, "\n{}", format!("🔍 {} Found:", labels.title).info().bold())?;
+    writeln!(out, "{}", "=".repeat(labels.title.len() + 8).info())
+}
+
+fn print_file_errors(out: &mut dyn Write, file_name: &str, report: &FileReport, style: &ContentStyle, labels: MatchLabels) -> io::Result<()> {
+    let truncation_note = if report.is_truncated() {
+        format!(", showing first {} of {}", report.entries.len(), report.match_count)
+    } else {
+        String::new()
+    };
+    writeln!(out, "\n{} {} ({} {}{})", 
+        "📄".info(),
+        file_name.bold(),
+        report.match_count,
+        labels.count(report.match_count),
+        truncation_note.warn())?;
+    if report.changed_during_scan {
+        writeln!(out, "  {}", "⚠️ Possibly incomplete — file changed during scan".warn())?;
+    }
+
+    let contents: Vec<&str> = report.entries.iter().map(|entry| entry.content.as_str()).collect();
+    let aligned = style.align.then(|| align_columns(&contents)).flatten();
+    // Aligned output pads the line numbers and timestamps to a common width too
+    let (number_width, time_width) = if style.align {
+        report.entries.iter().fold((0, 0), |(numbers, times), entry| (
+            numbers.max(entry.line_number.to_string().len()),
+            times.max(entry.format_timestamp().chars().count() + 2),
+        ))
+    } else {
+        (0, 0)
+    };
+
+    for (index, entry) in report.entries.iter().enumerate() {
+        let content = aligned.as_ref().map_or(entry.content.as_str(), |aligned| aligned[index].as_str());
+        let first_before = entry.line_number - entry.before.len();
+        for (i, line) in entry.before.iter().enumerate() {
+            writeln!(out, "  {}  {}", "│".info(), format!("{}- {}", first_before + i, style.clip(line)).dimmed())?;
+        }
+
+        let repeat_note = match entry.last_line_number {
+            Some(last) if entry.occurrences > 1 => format!(" (×{}{}, last on line {})",
+                entry.occurrences,
+                labels.repeats,
+                last),
+            _ => String::new(),
+        };
+        writeln!(out, "  {} {} - {} {}{}",
+            "└─".info(),
+            format!("Line {:<width$}", entry.line_number, width = number_width).warn(),
+            format!("{:<width$}", format!("[{}]", entry.format_timestamp()), width = time_width).accent(),
+            // The lines of a merged event line up under its first
+            colorize_content(content, entry.severity, style).replace('\n', "\n      "),
+            repeat_note.note())?;
+
+        // Trailing context hangs under the match, set apart from the lines leading up to it
+        for (i, line) in entry.after.iter().enumerate() {
+            writeln!(out, "     {} {}", "┆".info(), format!("{}- {}", entry.end_line_number() + 1 + i, style.clip(line)).dimmed())?;
+        }
     }
+
+    Ok(())
 }
 
-struct ScanStats {
-    total_files: usize,
-    processed_files: usize,
-    total_errors: usize,
-    skipped_files: usize,
-    large_files: usize,
+fn print_errors(out: &mut dyn Write, errors_by_file: &[(String, FileReport)], style: &ContentStyle, labels: MatchLabels) -> io::Result<()> {
+    if errors_by_file.is_empty() {
+        return print_no_matches(out, labels);
+    }
+
+    print_errors_heading(out, labels)?;
+    for (file_name, report) in errors_by_file {
+        print_file_errors(out, file_name, report, style, labels)?;
+    }
+
+    Ok(())
 }
 
-impl ScanStats {
-    fn new() -> Self {
-        Self {
-            total_files: 0,
-            processed_files: 0,
-            total_errors: 0,
-            skipped_files: 0,
-            large_files: 0,
+fn print_comparison(out: &mut dyn Write, comparison: &Comparison, style: &ContentStyle, labels: MatchLabels) -> io::Result<()> {
+    let new_count: usize = comparison.new.iter().map(|(_, entries)| entries.len()).sum();
+    if new_count == 0 {
+        writeln!(out, "\n{} {}", "✅".good(), format!("No new {} since the previous scan.", labels.many).good())?;
+    } else {
+        writeln!(out, "\n{}", format!("🆕 New since the previous scan: {}", new_count).info().bold())?;
+        for (file_name, entries) in &comparison.new {
+            writeln!(out, "\n{} {}", "📄".info(), file_name.bold())?;
+            for entry in entries {
+                writeln!(out, "  {} {} - [{}] {}",
+                    "└─".info(),
+                    format!("Line {}", entry.line_number).warn(),
+                    entry.format_timestamp().accent(),
+                    colorize_content(&entry.content, entry.severity, style))?;
+            }
         }
     }
 
-    fn print_summary(&self, duration: Duration) {
-        println!("\n{}", "📊 Scan Statistics:".cyan().bold());
-        println!("├─ Scan time: {} ms", duration.as_millis().to_string().cyan());
-        println!("├─ Total files scanned: {}", self.processed_files.to_string().green());
-        println!("├─ Total errors found: {}", self.total_errors.to_string().yellow());
-        println!("├─ Files skipped: {}", self.skipped_files.to_string().yellow());
-        println!("└─ Large files encountered: {}", self.large_files.to_string().yellow());
+    if !comparison.resolved.is_empty() {
+        writeln!(out, "\n{}", format!("✔️ No longer seen: {}", comparison.resolved.len()).good().bold())?;
+        for (file_name, content) in &comparison.resolved {
+            writeln!(out, "  {} {}: {}", "└─".info(), file_name, content.dimmed())?;
+        }
     }
+    writeln!(out, "\n{} {} {} seen in the previous scan too",
+        "🔁".info(),
+        comparison.persisting,
+        labels.count(comparison.persisting))
 }
 
-fn process_log_file(file_path: &Path) -> Result<Vec<LogEntry>> {
-    if !file_path.exists() {
-        return Err(AppError::IoError(io::Error::new(
-            io::ErrorKind::NotFound,
-            format!("File {:?} does not exist", file_path)
-        )));
+// Every match of the scan in time order; entries with the same timestamp,
+// such as those falling back to their file's mtime, keep their file order,
+// and any without a time at all come last
+fn matches_by_time(errors_by_file: &[(String, FileReport)]) -> Vec<(&str, &LogEntry)> {
+    let mut matches: Vec<(&str, &LogEntry)> = errors_by_file.iter()
+        .flat_map(|(file_name, report)| report.entries.iter().map(move |entry| (file_name.as_str(), entry)))
+        .collect();
+    matches.sort_by_key(|(_, entry)| (entry.timestamp.is_none(), entry.timestamp));
+    matches
+}
+
+// The earliest and latest matches for --first and --last
+fn print_time_slices(
+    out: &mut dyn Write,
+    errors_by_file: &[(String, FileReport)],
+    first: Option<usize>,
+    last: Option<usize>,
+    style: &ContentStyle,
+    labels: MatchLabels,
+) -> io::Result<()> {
+    let matches = matches_by_time(errors_by_file);
+    if matches.is_empty() {
+        return print_no_matches(out, labels);
     }
 
-    let file = fs::File::open(file_path).map_err(|e| match e.kind() {
-        io::ErrorKind::PermissionDenied => 
-            AppError::PermissionDenied(format!("Access denied to file {:?}", file_path)),
-        io::ErrorKind::InvalidData =>
-            AppError::EncodingError(format!("Invalid file encoding in {:?}", file_path)),
-        _ => AppError::FileProcessingError {
-            path: file_path.to_path_buf(),
-            error: e.to_string(),
-        },
-    })?;
+    if let Some(first) = first {
+        let shown = &matches[..first.min(matches.len())];
+        writeln!(out, "\n{}", format!("⏮️ First {} of {} {}:", shown.len(), matches.len(), labels.many).info().bold())?;
+        print_timed_matches(out, shown, style)?;
+    }
+    if let Some(last) = last {
+        let shown = &matches[matches.len() - last.min(matches.len())..];
+        writeln!(out, "\n{}", format!("⏭️ Last {} of {} {}:", shown.len(), matches.len(), labels.many).info().bold())?;
+        print_timed_matches(out, shown, style)?;
+    }
+    Ok(())
+}
 
-    let metadata = file.metadata().map_err(|e| AppError::FileProcessingError {
-        path: file_path.to_path_buf(),
-        error: format!("Failed to read file metadata: {}", e),
-    })?;
+fn print_timed_matches(out: &mut dyn Write, matches: &[(&str, &LogEntry)], style: &ContentStyle) -> io::Result<()> {
+    for (file_name, entry) in matches {
+        writeln!(out, "  {} {} {} {}",
+            "└─".info(),
+            format!("[{}]", entry.format_timestamp()).accent(),
+            format!("{}:{}", file_name, entry.line_number).warn(),
+            colorize_content(&entry.content, entry.severity, style))?;
+    }
+    Ok(())
+}
 
-    validate_file_size(metadata.len(), file_path)?;
+// A message that recurs across the scan, with the first line it was seen in
+struct RecurringMessage {
+    content: String,
+    severity: Option<Severity>,
+    count: usize,
+    files: Vec<String>,
+}
 
-    let file_size = metadata.len();
-    let is_large_file = file_size > 100_000_000;
+// Messages are grouped the way --group-similar does, ignoring numbers, ids
+// and addresses, and ranked by how often they occur
+fn top_messages(errors_by_file: &[(String, FileReport)], limit: usize) -> Vec<RecurringMessage> {
+    let mut messages: Vec<RecurringMessage> = Vec::new();
+    let mut index_by_key = HashMap::new();
+    for (file_name, report) in errors_by_file {
+        for entry in &report.entries {
+            let index = *index_by_key.entry(normalize_message(&entry.content)).or_insert_with(|| {
+                messages.push(RecurringMessage {
+                    content: entry.content.clone(),
+                    severity: entry.severity,
+                    count: 0,
+                    files: Vec::new(),
+                });
+                messages.len() - 1
+            });
+            let message = &mut messages[index];
+            message.count += entry.occurrences;
+            if message.files.last() != Some(file_name) {
+                message.files.push(file_name.clone());
+            }
+        }
+    }
 
-    if is_large_file {
-        eprintln!("{} {} ({}) - Processing may take time...",
-            "📦".yellow(),
-            "Large file detected".yellow().bold(),
-            format_size(file_size, BINARY).yellow());
+    // The sort is stable, so ties keep the order they were first seen in
+    messages.sort_by_key(|message| Reverse(message.count));
+    messages.truncate(limit);
+    messages
+}
+
+fn print_top_messages(out: &mut dyn Write, messages: &[RecurringMessage], style: &ContentStyle, labels: MatchLabels) -> io::Result<()> {
+    if messages.is_empty() {
+        return Ok(());
     }
 
-    let reader = io::BufReader::with_capacity(128 * 1024, file); // 128KB buffer
-    let mut error_lines = Vec::new();
-    let start_time = SystemTime::now();
+    writeln!(out, "\n{}", format!("🏆 Most frequent {}:", labels.many).info().bold())?;
+    for (rank, message) in messages.iter().enumerate() {
+        writeln!(out, "  {} {} {}",
+            format!("{}.", rank + 1).info(),
+            format!("{}×", message.count).warn(),
+            colorize_content(&message.content, message.severity, style))?;
 
-    for (line_num, line_result) in reader.lines().enumerate() {
-        if start_time.elapsed().map(|elapsed| elapsed > OPERATION_TIMEOUT).unwrap_or(false) {
-            return Err(AppError::TimeoutError(
-                format!("Processing of file {:?} timed out after {} seconds", 
-                    file_path, OPERATION_TIMEOUT.as_secs())
-            ));
+        const SHOWN_FILES: usize = 3;
+        let mut files = message.files[..message.files.len().min(SHOWN_FILES)].join(", ");
+        if message.files.len() > SHOWN_FILES {
+            files.push_str(&format!(" and {} more", message.files.len() - SHOWN_FILES));
         }
+        writeln!(out, "     {}", format!("in {}", files).dimmed())?;
+    }
 
-        match line_result {
-            Ok(line) => {
-                if line.to_lowercase().contains("error") {
-                    error_lines.push(LogEntry {
-                        line_number: line_num + 1,
-                        content: line,
-                        timestamp: metadata.modified().ok(),
-                    });
+    Ok(())
+}
+
+// Match counts summed up by the directory of each file below the scan root,
+// cut to its first `depth` levels, most first; files at the top level count
+// under ".". Goes by the relative path whatever --path-style shows.
+fn matches_by_directory(errors_by_file: &[(String, FileReport)], depth: Option<usize>) -> Vec<(String, usize)> {
+    let mut counts: HashMap<PathBuf, usize> = HashMap::new();
+    for (_, report) in errors_by_file {
+        let parent = report.relative_path.parent().unwrap_or(Path::new(""));
+        // The root of absolute paths doesn't count as a level
+        let mut levels = 0;
+        let directory: PathBuf = parent.components()
+            .take_while(|component| {
+                if matches!(component, Component::Normal(_)) {
+                    levels += 1;
                 }
+                depth.is_none_or(|depth| levels <= depth)
+            })
+            .collect();
+        *counts.entry(directory).or_insert(0) += report.match_count;
+    }
+
+    let mut counts: Vec<(String, usize)> = counts.into_iter()
+        .map(|(directory, count)| {
+            let name = if directory.as_os_str().is_empty() { ".".to_string() } else { directory.display().to_string() };
+            (name, count)
+        })
+        .collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| natural_cmp(&a.0, &b.0)));
+    counts
+}
+
+fn print_directory_counts(out: &mut dyn Write, directories: &[(String, usize)], total: usize, labels: MatchLabels) -> io::Result<()> {
+    if directories.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out, "\n{}", format!("📂 {} by directory:", labels.title).info().bold())?;
+    let width = directories.iter().map(|(_, count)| count.to_string().len()).max().unwrap_or(0);
+    for (directory, count) in directories {
+        let share = if total > 0 { *count as f64 / total as f64 * 100.0 } else { 0.0 };
+        writeln!(out, "  {} {} {} {}",
+            "└─".info(),
+            format!("{:>width$}", count, width = width).warn(),
+            format!("({:>3.0}%)", share).dimmed(),
+            directory)?;
+    }
+    Ok(())
+}
+
+fn print_counts(out: &mut dyn Write, errors_by_file: &[(String, FileReport)], labels: MatchLabels) -> io::Result<()> {
+    if errors_by_file.is_empty() {
+        return print_no_matches(out, labels);
+    }
+
+    let mut counts: Vec<_> = errors_by_file.iter()
+        .map(|(file_name, report)| (file_name, report.match_count))
+        .collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| natural_cmp(a.0, b.0)));
+
+    writeln!(out, "\n{}", format!("🔢 {} per file:", labels.title).info().bold())?;
+    for (file_name, count) in counts {
+        writeln!(out, "  {} {} {}", "└─".info(), count.to_string().warn(), file_name)?;
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct JsonFileReport<'a> {
+    path: &'a str,
+    match_count: usize,
+    truncated: bool,
+    changed_during_scan: bool,
+    errors: &'a [LogEntry],
+}
+
+#[derive(Serialize)]
+struct JsonSummary<'a> {
+    #[serde(flatten)]
+    stats: &'a ScanStats,
+    duration_ms: u128,
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    files: Vec<JsonFileReport<'a>>,
+    summary: JsonSummary<'a>,
+}
+
+fn print_json_report(out: &mut dyn Write, errors_by_file: &[(String, FileReport)], stats: &ScanStats, duration: Duration) -> Result<()> {
+    let report = JsonReport {
+        files: errors_by_file.iter()
+            .map(|(path, report)| JsonFileReport {
+                path,
+                match_count: report.match_count,
+                truncated: report.is_truncated(),
+                changed_during_scan: report.changed_during_scan,
+                errors: &report.entries,
+            })
+            .collect(),
+        summary: JsonSummary {
+            stats,
+            duration_ms: duration.as_millis(),
+        },
+    };
+
+    serde_json::to_writer_pretty(&mut *out, &report).map_err(io::Error::from)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn print_json_summary(out: &mut dyn Write, stats: &ScanStats, duration: Duration) -> Result<()> {
+    let summary = JsonSummary {
+        stats,
+        duration_ms: duration.as_millis(),
+    };
+    serde_json::to_writer(&mut *out, &summary).map_err(io::Error::from)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct NdjsonEntry<'a> {
+    file: &'a str,
+    #[serde(flatten)]
+    entry: &'a LogEntry,
+}
+
+fn print_ndjson_entries(out: &mut dyn Write, file_name: &str, report: &FileReport) -> io::Result<()> {
+    for entry in &report.entries {
+        serde_json::to_writer(&mut *out, &NdjsonEntry { file: file_name, entry })?;
+        writeln!(out)?;
+    }
+    // Flushed per file so that a pipeline downstream sees the lines right away
+    out.flush()
+}
+
+fn print_csv_report(out: &mut dyn Write, errors_by_file: &[(String, FileReport)]) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record(["file", "line_number", "timestamp", "content"]).map_err(io::Error::from)?;
+
+    for (path, report) in errors_by_file {
+        for entry in &report.entries {
+            writer.write_record([
+                path.as_str(),
+                &entry.line_number.to_string(),
+                &entry.format_timestamp(),
+                &entry.content,
+            ]).map_err(io::Error::from)?;
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+fn print_followed_match(out: &mut dyn Write, file_name: &str, entry: &LogEntry, style: &ContentStyle) -> io::Result<()> {
+    writeln!(out, "{} {} {} - [{}] {}",
+        "📄".info(),
+        file_name.bold(),
+        format!("Line {}", entry.line_number).warn(),
+        entry.format_timestamp().accent(),
+        colorize_content(&entry.content, entry.severity, style))
+}
+
+// Polls the followed files until Ctrl-C sets `stop`
+fn follow_files(out: &mut dyn Write, mut follower: Follower, mut notifier: Option<Notifier>, style: &ContentStyle, stop: &AtomicBool) -> Result<()> {
+    while !stop.load(Ordering::Relaxed) {
+        for (file_name, entry) in follower.poll() {
+            print_followed_match(out, &file_name, &entry, style)?;
+            if let Some(notifier) = notifier.as_mut() {
+                notifier.notify_match(&file_name, &entry.content);
             }
-            Err(e) => {
-                match e.kind() {
-                    io::ErrorKind::InvalidData => {
-                        continue; // Skip invalid UTF-8 lines
-                    },
-                    _ => {
-                        eprintln!("{} Line {} in {:?}: {}",
-                            "⚠️".yellow(),
-                            line_num + 1,
-                            file_path,
-                            e.to_string().red());
-                    }
+        }
+        out.flush()?;
+        if let Some(notifier) = notifier.as_mut() {
+            notifier.flush();
+        }
+        std::thread::sleep(FOLLOW_POLL_INTERVAL);
+    }
+    Ok(())
+}
+
+// Matches lines from stdin until it ends or Ctrl-C, for --stdin-stream
+fn stream_stdin(cli: &Cli, mut options: ScanOptions, output_file: Option<fs::File>) -> Result<i32> {
+    if io::stdin().is_terminal() {
+        eprintln!("{} Reading log lines from the terminal, end with Ctrl-D", "⌨️".info());
+    }
+    let stop = install_interrupt_handler(None)?;
+    options.stop = Some(Arc::clone(&stop));
+
+    let mut out = output_writer(output_file);
+    let labels = MatchLabels::new(cli.invert, cli.group_similar);
+    let style = ContentStyle { options: &options, truncate: cli.truncate, align: false };
+    let mut match_count = 0;
+    let lines_read = follow_reader(io::stdin().lock(), &options, |entry| {
+        match_count += 1;
+        if cli.quiet {
+            return Ok(());
+        }
+        if cli.format == OutputFormat::Ndjson {
+            serde_json::to_writer(&mut *out, &NdjsonEntry { file: STDIN_NAME, entry: &entry })?;
+            writeln!(out)?;
+        } else {
+            print_followed_match(&mut *out, STDIN_NAME, &entry, &style)?;
+        }
+        // Each match is flushed so a pipeline downstream sees it right away
+        out.flush()
+    })?;
+
+    let over_threshold = cli.fail_threshold.filter(|threshold| match_count > *threshold);
+    // The summary would get mixed into ndjson output on stdout
+    let mut summary: Box<dyn Write> = if cli.format == OutputFormat::Text { out } else { Box::new(io::stderr()) };
+    writeln!(summary, "\n{} Read {} {} from stdin, {} {}",
+        "📊".info(),
+        lines_read,
+        if lines_read == 1 { "line" } else { "lines" },
+        match_count.to_string().warn(),
+        labels.count(match_count))?;
+    if let Some(threshold) = over_threshold {
+        print_threshold_exceeded(&mut *summary, match_count, threshold, labels)?;
+    }
+    summary.flush()?;
+
+    if stop.load(Ordering::Relaxed) {
+        Ok(EXIT_INTERRUPTED)
+    } else if (cli.exit_on_match && match_count > 0) || over_threshold.is_some() {
+        Ok(EXIT_MATCHES)
+    } else {
+        Ok(EXIT_CLEAN)
+    }
+}
+
+const SPINNER_TICK: Duration = Duration::from_millis(100);
+
+// indicatif colors its bars on its own, so their templates follow the theme separately
+fn themed_template(template: &str, theme: Theme) -> String {
+    match theme {
+        _ if !colored::control::SHOULD_COLORIZE.should_colorize() => template.replace(":.green", "").replace(".cyan/blue", ""),
+        Theme::Dark => template.to_string(),
+        Theme::Light => template.replace(":.green", ":.blue").replace(".cyan/blue", ".blue/black"),
+        Theme::Mono => template.replace(":.green", "").replace(".cyan/blue", ""),
+    }
+}
+
+// The first Ctrl-C sets the returned flag so the scan can wind down and still
+// report what it found; a second one saves the --resume checkpoint and exits
+// right away
+fn install_interrupt_handler(checkpoint: Option<Arc<Checkpoint>>) -> Result<Arc<AtomicBool>> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let handler_stop = Arc::clone(&stop);
+    ctrlc::set_handler(move || {
+        if handler_stop.swap(true, Ordering::Relaxed) {
+            // It is otherwise only saved every so often while files finish
+            if let Some(checkpoint) = &checkpoint {
+                if let Err(e) = checkpoint.save() {
+                    eprintln!("{} Cannot save resume file: {}", "⚠️".warn(), e.to_string().warn());
                 }
             }
+            process::exit(EXIT_INTERRUPTED);
+        }
+    })
+    .map_err(|e| AppError::InvalidInput(format!("Failed to install the Ctrl-C handler: {}", e)))?;
+    Ok(stop)
+}
+
+// Where results go: the --output file if there is one, stdout otherwise
+fn output_writer(output_file: Option<fs::File>) -> Box<dyn Write> {
+    match output_file {
+        Some(file) => Box::new(io::BufWriter::new(file)),
+        None => Box::new(io::stdout().lock()),
+    }
+}
+
+fn open_output_file(path: &Path, append: bool) -> Result<fs::File> {
+    fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::PermissionDenied =>
+                AppError::PermissionDenied(format!("Cannot write to output file {:?}", path)),
+            _ => AppError::FileProcessingError {
+                path: path.to_path_buf(),
+                error: e.to_string(),
+            },
+        })
+}
+
+// Reads newline-separated paths from a file, or from stdin for "-"
+fn read_file_list(list: &Path) -> Result<Vec<PathBuf>> {
+    let contents = if list == Path::new("-") {
+        io::read_to_string(io::stdin())?
+    } else {
+        fs::read_to_string(list).map_err(|e| AppError::FileProcessingError {
+            path: list.to_path_buf(),
+            error: e.to_string(),
+        })?
+    };
+
+    Ok(contents.lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+// Paths are shown relative to a single root, and in full when there are
+// several so that files with the same relative path stay apart
+fn collect_from_directories(cli: &Cli, roots: Vec<PathBuf>, options: &ScanOptions, decorated: bool) -> Result<(PathBuf, CollectedFiles)> {
+    for root in &roots {
+        if decorated {
+            eprintln!("\n{} Scanning directory: {}", "📂".info(), root.display());
         }
+
+        if !root.exists() {
+            return Err(AppError::IoError(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("❌ Directory {} does not exist", root.display())
+            )));
+        }
+    }
+
+    // Like the scan's progress bar, the spinner would redraw over --verbose messages
+    let spinner = if decorated && cli.verbose == 0 {
+        ProgressBar::new_spinner()
+    } else {
+        ProgressBar::hidden()
+    };
+    spinner.set_style(ProgressStyle::default_spinner()
+        .template(&themed_template("{spinner:.green} {msg} [{elapsed}]", cli.theme))
+        .unwrap());
+    spinner.set_message("Scanning directory tree...");
+    spinner.enable_steady_tick(SPINNER_TICK);
+    let collect_start = Instant::now();
+    let collect_options = ScanOptions { collect_progress: Some(spinner.clone()), ..options.clone() };
+    let collected = collect_files_from(&roots, &collect_options);
+    spinner.finish_and_clear();
+    let collected = collected?;
+    if decorated {
+        eprintln!("{} Found {} files in {} directories in {:.1}s",
+            "🔍".info(),
+            collected.files.len(),
+            collected.dirs_visited(),
+            collect_start.elapsed().as_secs_f64());
+    }
+    let log_dir_path = if roots.len() == 1 { roots[0].clone() } else { PathBuf::new() };
+
+    if decorated && !cli.excludes.is_empty() {
+        eprintln!("{} Excluded {} paths matching --exclude patterns", "🚫".info(), collected.excluded);
     }
+    if decorated && cli.modified_within.is_some() {
+        eprintln!("{} Left out {} files not modified within the --modified-within window", "🕰️".info(), collected.stale);
+    }
+    if decorated && !collected.skipped_symlinks.is_empty() {
+        eprintln!("{} Skipped {} symlinked directories{}",
+            "🔗".info(),
+            collected.skipped_symlinks.len(),
+            if cli.follow_symlinks { " already scanned" } else { " (use --follow-symlinks to scan them)" });
+    }
+
+    Ok((log_dir_path, collected))
+}
+
+// A single file is scanned as given, without walking a directory or
+// checking that it looks like text
+fn collect_single_file(path: PathBuf, decorated: bool) -> (PathBuf, CollectedFiles) {
+    if decorated {
+        eprintln!("\n{} Scanning file: {}", "📄".info(), path.display());
+    }
+    let root = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    (root, collect_listed_files(vec![path]))
+}
+
+fn print_dry_run(out: &mut dyn Write, root: &Path, files: &[PathBuf], options: &ScanOptions) -> io::Result<()> {
+    writeln!(out, "\n{}", "📁 Files that would be scanned:".info().bold())?;
 
-    Ok(error_lines)
+    let mut total_size = 0;
+    for (i, file) in files.iter().enumerate() {
+        let size = fs::metadata(file).map(|metadata| metadata.len()).unwrap_or(0);
+        total_size += size;
+        let size_note = if size > options.max_size { " - over --max-size, would be skipped" } else { "" };
+        writeln!(out, "  {} {} {} ({}){}",
+            "└─".info(),
+            format!("[{:02}]", i + 1).accent(),
+            options.path_style.display(file, root),
+            format_size(size, BINARY).info(),
+            size_note.warn())?;
+    }
+
+    writeln!(out, "\n{} {} files, {} in total (dry run, nothing was scanned)",
+        "📦".info(),
+        files.len().to_string().good(),
+        format_size(total_size, BINARY).good())?;
+    Ok(())
 }
 
-fn get_user_confirmation() -> Result<bool> {
+fn get_user_confirmation(assume_yes: bool) -> Result<bool> {
+    if assume_yes {
+        return Ok(true);
+    }
+
     let mut attempts = 0;
     const MAX_ATTEMPTS: u32 = 3;
 
     while attempts < MAX_ATTEMPTS {
-        print!("\n{} Proceed with scanning? ({}/{}, default: y) ",
-            "❓".cyan(),
-            "Y".green().bold(),
-            "n".red().bold());
-        
-        if io::stdout().flush().is_err() {
-            eprintln!("{} Failed to flush stdout", "⚠️".yellow());
-        }
+        eprint!("\n{} Proceed with scanning? ({}/{}, default: y) ",
+            "❓".info(),
+            "Y".good().bold(),
+            "n".bad().bold());
 
         let mut buffer = String::new();
         match io::stdin().read_line(&mut buffer) {
@@ -222,13 +1601,13 @@ fn get_user_confirmation() -> Result<bool> {
                     "" | "y" | "yes" => return Ok(true),
                     "n" | "no" => return Ok(false),
                     _ => {
-                        eprintln!("{} Please enter 'y' or 'n'", "⚠️".yellow());
+                        eprintln!("{} Please enter 'y' or 'n'", "⚠️".warn());
                         attempts += 1;
                     }
                 }
             }
             Err(e) => {
-                eprintln!("{} Failed to read input: {}", "⚠️".yellow(), e);
+                eprintln!("{} Failed to read input: {}", "⚠️".warn(), e);
                 attempts += 1;
             }
         }
@@ -238,69 +1617,82 @@ fn get_user_confirmation() -> Result<bool> {
 }
 
 fn print_header() {
-    println!("\n{}", "🔍 RustWatch - Log Monitor".green().bold());
-    println!("{}", "=======================".green());
-    println!("{} {}", "Version:".cyan(), env!("CARGO_PKG_VERSION"));
-    println!("{} {}", "Time:".cyan(), Local::now().format("%Y-%m-%d %H:%M:%S"));
-    println!("\n{}", "RustWatch vigilantly monitors your logs for errors and issues.".italic());
-    println!("{}", "Scan system logs or any directory with lightning speed.".italic());
+    eprintln!("\n{}", "🔍 RustWatch - Log Monitor".good().bold());
+    eprintln!("{}", "=======================".good());
+    eprintln!("{} {}", "Version:".info(), env!("CARGO_PKG_VERSION"));
+    eprintln!("{} {}", "Time:".info(), Local::now().format("%Y-%m-%d %H:%M:%S"));
+    eprintln!("\n{}", "RustWatch vigilantly monitors your logs for errors and issues.".italic());
+    eprintln!("{}", "Scan system logs or any directory with lightning speed.".italic());
+}
+
+// The system log directory offered as option 1 in the location menu
+#[cfg(target_os = "macos")]
+fn default_scan_directory() -> PathBuf {
+    // /var/log is normally a symlink to /private/var/log, but not always
+    ["/var/log", "/private/var/log"].iter()
+        .map(PathBuf::from)
+        .find(|path| path.is_dir())
+        .unwrap_or_else(|| PathBuf::from("/var/log"))
+}
+
+#[cfg(windows)]
+fn default_scan_directory() -> PathBuf {
+    // Most services that log to files rather than the Event Log write under ProgramData
+    std::env::var_os("ProgramData")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(r"C:\ProgramData"))
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn default_scan_directory() -> PathBuf {
+    PathBuf::from("/var/log")
 }
 
 fn get_scan_directory() -> Result<PathBuf> {
-    println!("\n{}", "📂 Select scan location:".cyan().bold());
-    println!("  {} Default location (/var/log) {}", "1.".cyan().bold(), "(default)".cyan().italic());
-    println!("  {} Custom directory", "2.".cyan());
+    let default_dir = default_scan_directory();
+    eprintln!("\n{}", "📂 Select scan location:".info().bold());
+    eprintln!("  {} Default location ({}) {}", "1.".info().bold(), default_dir.display(), "(default)".info().italic());
+    eprintln!("  {} Custom directory or file", "2.".info());
 
     let mut attempts = 0;
     const MAX_ATTEMPTS: u32 = 3;
 
     while attempts < MAX_ATTEMPTS {
-        print!("\n{} Choose an option (1/2, default: 1): ", "❓".cyan());
-        if io::stdout().flush().is_err() {
-            eprintln!("{} Failed to flush stdout", "⚠️".yellow());
-        }
+        eprint!("\n{} Choose an option (1/2, default: 1): ", "❓".info());
 
         let mut buffer = String::new();
         match io::stdin().read_line(&mut buffer) {
             Ok(_) => {
                 match buffer.trim() {
-                    "" | "1" => return Ok(PathBuf::from("/var/log")),
+                    "" | "1" => return Ok(default_dir),
                     "2" => {
-                        print!("\n{} Enter directory path: ", "📁".cyan());
-                        if io::stdout().flush().is_err() {
-                            eprintln!("{} Failed to flush stdout", "⚠️".yellow());
-                        }
+                        eprint!("\n{} Enter directory or file path: ", "📁".info());
 
                         let mut path_buffer = String::new();
                         match io::stdin().read_line(&mut path_buffer) {
                             Ok(_) => {
                                 let path = PathBuf::from(path_buffer.trim());
                                 if !path.exists() {
-                                    eprintln!("{} Directory does not exist", "❌".red());
-                                    attempts += 1;
-                                    continue;
-                                }
-                                if !path.is_dir() {
-                                    eprintln!("{} Path is not a directory", "❌".red());
+                                    eprintln!("{} Path does not exist", "❌".bad());
                                     attempts += 1;
                                     continue;
                                 }
                                 return Ok(path);
                             }
                             Err(e) => {
-                                eprintln!("{} Failed to read input: {}", "⚠️".yellow(), e);
+                                eprintln!("{} Failed to read input: {}", "⚠️".warn(), e);
                                 attempts += 1;
                             }
                         }
                     }
                     _ => {
-                        eprintln!("{} Please enter 1 or 2", "⚠️".yellow());
+                        eprintln!("{} Please enter 1 or 2", "⚠️".warn());
                         attempts += 1;
                     }
                 }
             }
             Err(e) => {
-                eprintln!("{} Failed to read input: {}", "⚠️".yellow(), e);
+                eprintln!("{} Failed to read input: {}", "⚠️".warn(), e);
                 attempts += 1;
             }
         }
@@ -309,237 +1701,436 @@ fn get_scan_directory() -> Result<PathBuf> {
     Err(AppError::InvalidInput("Maximum attempts exceeded while selecting directory".to_string()))
 }
 
-fn is_text_file(path: &Path) -> bool {
-    // Check extension first
-    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-        if TEXT_FILE_EXTENSIONS.contains(&ext.to_lowercase().as_str()) {
-            return true;
+fn main() {
+    let code = match run() {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{} {}", "Error:".bad().bold(), e);
+            EXIT_FAILURE
         }
-    }
+    };
+    process::exit(code);
+}
 
-    // If no extension or not in list, try to read first few bytes
-    if let Ok(mut file) = fs::File::open(path) {
-        let mut buffer = [0; 512];
-        if let Ok(size) = file.read(&mut buffer) {
-            if size == 0 { return false; }  // Empty file
-            
-            // Check for null bytes and high concentration of non-ASCII chars
-            let null_bytes = buffer[..size].iter().filter(|&&b| b == 0).count();
-            let non_ascii = buffer[..size].iter().filter(|&&b| b > 127).count();
-            
-            // If more than 1% null bytes or 30% non-ASCII, probably binary
-            return (null_bytes as f32 / size as f32) < 0.01 
-                && (non_ascii as f32 / size as f32) < 0.3;
-        }
+// Command-line flags win over the config file, which wins over the built-in defaults
+fn parse_cli() -> Result<Cli> {
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if let Some(config) = Config::load(cli.config.as_deref())? {
+        config.apply(&mut cli, &matches)?;
     }
-    false
+    Ok(cli)
 }
 
-fn collect_files_recursive(dir_path: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
-    match fs::read_dir(dir_path) {
-        Ok(entries) => {
-            for entry in entries {
-                match entry {
-                    Ok(entry) => {
-                        let path = entry.path();
-                        if path.is_file() {
-                            // Only add if it's a text file
-                            if is_text_file(&path) {
-                                files.push(path);
-                            } else {
-                                // Optional: uncomment to see which files are skipped
-                                // eprintln!("{} Skipping non-text file: {}",
-                                //     "ℹ️".blue(),
-                                //     path.display());
-                            }
-                        } else if path.is_dir() {
-                            // If we can't access a subdirectory, log it and continue
-                            if let Err(e) = collect_files_recursive(&path, files) {
-                                match e {
-                                    AppError::PermissionDenied(_) => {
-                                        eprintln!("{} Skipping directory {}: {}",
-                                            "⚠️".yellow(),
-                                            path.display(),
-                                            "Permission denied".yellow());
-                                    },
-                                    _ => {
-                                        eprintln!("{} Error accessing directory {}: {}",
-                                            "⚠️".yellow(),
-                                            path.display(),
-                                            e.to_string().red());
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        match e.kind() {
-                            io::ErrorKind::PermissionDenied => {
-                                eprintln!("{} Skipping entry in {}: {}",
-                                    "⚠️".yellow(),
-                                    dir_path.display(),
-                                    "Permission denied".yellow());
-                            },
-                            _ => {
-                                eprintln!("{} Error accessing entry in {}: {}",
-                                    "⚠️".yellow(),
-                                    dir_path.display(),
-                                    e.to_string().red());
-                            }
-                        }
-                    }
-                }
-            }
-            Ok(())
-        }
-        Err(e) => {
-            match e.kind() {
-                io::ErrorKind::PermissionDenied => {
-                    Err(AppError::PermissionDenied(
-                        format!("Cannot access directory {}: Permission denied", dir_path.display())
-                    ))
-                },
-                _ => Err(AppError::IoError(e))
-            }
-        }
+fn run() -> Result<i32> {
+    let cli = parse_cli()?;
+    let mut options = build_scan_options(&cli)?;
+    if cli.follow && cli.format != OutputFormat::Text {
+        return Err(AppError::InvalidInput("--follow only supports the text output format".to_string()));
     }
-}
+    if cli.stream && !matches!(cli.format, OutputFormat::Text | OutputFormat::Ndjson) {
+        return Err(AppError::InvalidInput("--stream only supports the text and ndjson output formats".to_string()));
+    }
+    if cli.stdin_stream && !matches!(cli.format, OutputFormat::Text | OutputFormat::Ndjson) {
+        return Err(AppError::InvalidInput("--stdin-stream only supports the text and ndjson output formats".to_string()));
+    }
+    if cli.top.is_some() && cli.format == OutputFormat::Ndjson {
+        return Err(AppError::InvalidInput("--top needs every file scanned first, which --format ndjson doesn't wait for".to_string()));
+    }
+    if cli.top_errors.is_some() && cli.format != OutputFormat::Text {
+        return Err(AppError::InvalidInput("--top-errors only supports the text output format".to_string()));
+    }
+    if cli.group_by_dir && cli.format != OutputFormat::Text {
+        return Err(AppError::InvalidInput("--group-by-dir only supports the text output format".to_string()));
+    }
+    if cli.rate && cli.format != OutputFormat::Text {
+        return Err(AppError::InvalidInput("--rate only supports the text output format".to_string()));
+    }
+    if cli.stats && cli.format != OutputFormat::Text {
+        return Err(AppError::InvalidInput("--stats only supports the text output format".to_string()));
+    }
+    if cli.list_scanned && !matches!(cli.format, OutputFormat::Text | OutputFormat::Json) {
+        return Err(AppError::InvalidInput("--list-scanned only supports the text and json output formats".to_string()));
+    }
+    if cli.split_output.is_some() && !matches!(cli.format, OutputFormat::Text | OutputFormat::Json) {
+        return Err(AppError::InvalidInput("--split-output only supports the text and json output formats".to_string()));
+    }
+    if (cli.first.is_some() || cli.last.is_some()) && cli.format != OutputFormat::Text {
+        return Err(AppError::InvalidInput("--first and --last only support the text output format".to_string()));
+    }
+    if cli.compare.is_some() && cli.format != OutputFormat::Text {
+        return Err(AppError::InvalidInput("--compare only supports the text output format".to_string()));
+    }
+    let previous = cli.compare.as_deref().map(PreviousScan::load).transpose()?;
+    if let Some(threads) = cli.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads.get())
+            .build_global()
+            .map_err(|e| AppError::InvalidInput(format!("Failed to start {} worker threads: {}", threads, e)))?;
+    }
+    // Progress messages go to stderr, and only alongside the text report
+    let decorated = cli.format == OutputFormat::Text && !cli.quiet && !cli.quiet_if_clean && !cli.summary_json && !cli.metrics;
 
-fn main() -> Result<()> {
-    print_header();
+    // Open the output file up front so a bad path fails before the scan starts
+    let output_file = cli.output.as_deref()
+        .map(|path| open_output_file(path, cli.append))
+        .transpose()?;
+    cli.theme.apply();
+    // Colors are already off when stdout isn't a terminal or NO_COLOR is set
+    if cli.no_color || output_file.is_some() || !io::stdout().is_terminal() {
+        colored::control::set_override(false);
+    }
 
-    #[cfg(target_os = "linux")]
-    if let Ok(is_root) = user_privileges::is_root_user() {
-        if !is_root {
-            eprintln!("\n{} {} This tool is not running with sudo privileges.",
-                "⚠️".yellow(),
-                "Warning:".yellow().bold());
-            eprintln!("{} Some directories may not be accessible. Run with sudo for full access.\n",
-                " ".repeat(9));
-        }
+    if cli.stdin_stream {
+        return stream_stdin(&cli, options, output_file);
     }
 
-    let log_dir_path = get_scan_directory()?;
-    println!("\n{} Scanning directory: {}", "📂".cyan(), log_dir_path.display());
+    if decorated {
+        print_header();
+    }
 
-    if !log_dir_path.exists() {
-        return Err(AppError::IoError(io::Error::new(
-            io::ErrorKind::NotFound,
-            format!("❌ Directory {} does not exist", log_dir_path.display())
-        )));
+    #[cfg(any(target_os = "linux", target_os = "macos", windows))]
+    if let Ok(is_elevated) = user_privileges::is_elevated() {
+        // Running unprivileged is expected in a cron job, not worth a mail
+        if !is_elevated && !cli.quiet_if_clean {
+            eprintln!("\n{} {} {}",
+                "⚠️".warn(),
+                "Warning:".warn().bold(),
+                user_privileges::MISSING);
+            eprintln!("{} Some directories may not be accessible. {}\n",
+                " ".repeat(9),
+                user_privileges::HINT);
+        }
+    }
+
+    let use_journal = cli.journald && journal_available();
+    if cli.journald && !use_journal {
+        eprintln!("{} {}",
+            "⚠️".warn(),
+            "journalctl is not available, scanning log files instead of the journal".warn());
     }
 
-    let mut log_files = Vec::new();
-    println!("{}", "🔍 Scanning directory tree...".cyan());
-    collect_files_recursive(&log_dir_path, &mut log_files)?;
+    let mut traversal_start = Instant::now();
+    let (log_dir_path, collected) = match &cli.files_from {
+        _ if use_journal => {
+            if decorated {
+                eprintln!("\n{} Scanning the systemd journal", "📓".info());
+            }
+            (PathBuf::new(), collect_journal())
+        }
+        Some(list) => {
+            // Listed files are scanned as given, relative to --dir if set
+            let root = cli.dir.clone().unwrap_or_else(|| PathBuf::from("."));
+            let collected = collect_listed_files(read_file_list(list)?);
+            for path in &collected.missing {
+                eprintln!("{} Skipping {}: {}", "⚠️".warn(), path.display(), "file not found".warn());
+            }
+            (root, collected)
+        }
+        // Explicit directories or patterns win over --dir
+        None if cli.patterns.len() == 1 && Path::new(&cli.patterns[0]).is_file() => {
+            collect_single_file(PathBuf::from(&cli.patterns[0]), decorated)
+        }
+        None if !cli.patterns.is_empty() => {
+            let (dirs, globs): (Vec<&String>, Vec<&String>) = cli.patterns.iter()
+                .partition(|pattern| Path::new(pattern).is_dir());
+            if globs.is_empty() {
+                let roots: Vec<PathBuf> = dirs.into_iter().map(PathBuf::from).collect();
+                collect_from_directories(&cli, roots, &options, decorated)?
+            } else if let Some(dir) = dirs.first() {
+                return Err(AppError::InvalidInput(format!(
+                    "Directories and glob patterns can't be mixed; use '{}/**/*' to match everything in {}",
+                    dir.trim_end_matches('/'), dir
+                )));
+            } else {
+                // --dir then only sets the display root
+                let root = cli.dir.clone().unwrap_or_else(|| PathBuf::from("."));
+                (root, collect_glob_files(&cli.patterns, &options)?)
+            }
+        }
+        None => {
+            let dir = match cli.dir.clone() {
+                Some(dir) => dir,
+                None => get_scan_directory()?,
+            };
+            // Time spent answering the prompt isn't traversal
+            traversal_start = Instant::now();
+            if dir.is_file() {
+                collect_single_file(dir, decorated)
+            } else {
+                collect_from_directories(&cli, vec![dir], &options, decorated)?
+            }
+        }
+    };
+    let traversal_time = traversal_start.elapsed();
 
-    if log_files.is_empty() {
-        return Err(AppError::IoError(io::Error::new(
-            io::ErrorKind::Other,
+    if collected.files.is_empty() {
+        return Err(AppError::IoError(io::Error::other(
             "❌ No readable files found"
         )));
     }
+    if collected.limit_reached {
+        eprintln!("{} {}",
+            "⚠️".warn(),
+            format!("Stopped collecting at the --max-files limit of {} files, results may be incomplete",
+                collected.files.len()).warn());
+    }
 
-    log_files.sort_by(|a, b| a.display().to_string().cmp(&b.display().to_string()));
+    if cli.dry_run {
+        let mut out = output_writer(output_file);
+        print_dry_run(&mut *out, &log_dir_path, &collected.files, &options)?;
+        out.flush()?;
+        return Ok(EXIT_CLEAN);
+    }
 
-    println!("\n{}", "📁 Files to be scanned:".cyan().bold());
-    for (i, file) in log_files.iter().enumerate() {
-        let display_path = file.strip_prefix(&log_dir_path)
-            .unwrap_or(file)
-            .display();
-        println!("  {} {} {}", 
-            "└─".cyan(),
-            format!("[{:02}]", i + 1).blue(),
-            display_path);
+    if decorated {
+        eprintln!("\n{}", "📁 Files to be scanned:".info().bold());
+        for (i, file) in collected.files.iter().enumerate() {
+            let display_path = options.path_style.display(file, &log_dir_path);
+            eprintln!("  {} {} {}", 
+                "└─".info(),
+                format!("[{:02}]", i + 1).accent(),
+                display_path);
+        }
     }
 
-    if !get_user_confirmation()? {
-        println!("{} {}", "✋".yellow(), "Scan cancelled by user.".yellow());
-        return Ok(());
+    // stdin already held the file list, so there's nothing left to answer with
+    let reading_stdin = cli.files_from.as_deref() == Some(Path::new("-"));
+    if !get_user_confirmation(cli.yes || reading_stdin)? {
+        eprintln!("{} {}", "✋".warn(), "Scan cancelled by user.".warn());
+        return Ok(EXIT_CLEAN);
     }
 
-    println!("\n{}", "🚀 Starting scan...".cyan().bold());
-    let start_time = Instant::now();
+    if decorated {
+        if let Some(checkpoint) = &options.checkpoint {
+            let finished = collected.files.iter().filter(|file| checkpoint.contains(file)).count();
+            if finished > 0 {
+                eprintln!("\n{} Resuming: {} of {} files were finished before and are not scanned again",
+                    "⏯️".info(),
+                    finished,
+                    collected.files.len());
+            }
+        }
+        eprintln!("\n{}", "🚀 Starting scan...".info().bold());
+    }
+    let stop = install_interrupt_handler(options.checkpoint.clone())?;
+    options.stop = Some(Arc::clone(&stop));
 
-    let pb = ProgressBar::new(log_files.len() as u64);
+    // The bar would keep redrawing over the --verbose messages
+    let pb = if decorated && cli.verbose == 0 {
+        ProgressBar::new(0)
+    } else {
+        ProgressBar::hidden()
+    };
+    let template = match cli.progress {
+        ProgressUnit::Files => "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})",
+        ProgressUnit::Bytes => "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {binary_bytes}/{binary_total_bytes} ({binary_bytes_per_sec}, {eta})",
+    };
     pb.set_style(ProgressStyle::default_bar()
-        .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})")
+        .template(&themed_template(template, cli.theme))
         .unwrap()
         .progress_chars("█▇▆▅▄▃▂▁"));
 
-    let mut stats = ScanStats::new();
-    stats.total_files = log_files.len();
+    // With --progress bytes, each finished file advances the bar by its size
+    let file_sizes: HashMap<&Path, u64> = match cli.progress {
+        ProgressUnit::Files => HashMap::new(),
+        ProgressUnit::Bytes => collected.files.iter()
+            .map(|path| (path.as_path(), fs::metadata(path).map_or(0, |metadata| metadata.len())))
+            .collect(),
+    };
+    pb.set_length(match cli.progress {
+        ProgressUnit::Files => collected.files.len() as u64,
+        ProgressUnit::Bytes => file_sizes.values().sum(),
+    });
+    let advance = |path: &Path| pb.inc(match cli.progress {
+        ProgressUnit::Files => 1,
+        ProgressUnit::Bytes => file_sizes.get(path).copied().unwrap_or(0),
+    });
 
-    // Process files in parallel
-    let results: Vec<_> = log_files.par_iter()
-        .map(|file_path| {
-            let result = process_log_file(file_path);
-            pb.inc(1);
-            (file_path, result)
-        })
-        .collect();
-
-    pb.finish_with_message("✅ Scan complete");
+    let mut out = output_writer(output_file);
+    let labels = MatchLabels::new(cli.invert, cli.group_similar);
+    let style = ContentStyle { options: &options, truncate: cli.truncate, align: cli.align };
 
-    let mut errors_by_file = Vec::new();
-
-    for (file_path, result) in results {
-        match result {
-            Ok(error_lines) => {
-                if !error_lines.is_empty() {
-                    let display_path = file_path.strip_prefix(&log_dir_path)
-                        .unwrap_or(file_path)
-                        .display()
-                        .to_string();
-                    stats.total_errors += error_lines.len();
-                    errors_by_file.push((display_path, error_lines));
-                }
-                stats.processed_files += 1;
-            }
-            Err(e) => {
-                eprintln!("{} {}: {}",
-                    "❌".red(),
-                    file_path.display(),
-                    e.to_string().red());
-                stats.skipped_files += 1;
+    // Time spent writing results, reported by --stats
+    let mut print_time = Duration::ZERO;
+    let mut report = if cli.stream || cli.format == OutputFormat::Ndjson {
+        // Print each file's matches as soon as it's done instead of holding
+        // them all until the end
+        let mut printed_any = false;
+        let mut write_result = Ok(());
+        let report = scan_files_streaming(&log_dir_path, &collected, &options, advance, |file_name, file_report| {
+            if cli.quiet || write_result.is_err() {
+                return;
             }
+            let print_start = Instant::now();
+            write_result = pb.suspend(|| {
+                if cli.format == OutputFormat::Ndjson {
+                    return print_ndjson_entries(&mut *out, &file_name, &file_report);
+                }
+                if !printed_any {
+                    print_errors_heading(&mut *out, labels)?;
+                    printed_any = true;
+                }
+                print_file_errors(&mut *out, &file_name, &file_report, &style, labels)?;
+                out.flush()
+            });
+            print_time += print_start.elapsed();
+        });
+        write_result?;
+        if !printed_any && !cli.quiet && !cli.quiet_if_clean && cli.format == OutputFormat::Text {
+            print_no_matches(&mut *out, labels)?;
         }
+        report
+    } else {
+        scan_files(&log_dir_path, &collected, &options, advance)
+    };
+
+    pb.finish_with_message("✅ Scan complete");
+
+    if let Some(state) = &options.incremental {
+        state.save(&state_file_path(&cli)?)?;
+    }
+    if let Some(checkpoint) = &options.checkpoint {
+        checkpoint.save()?;
+    }
+
+    for (file_path, e) in &report.failures {
+        eprintln!("{} {}: {}",
+            "❌".bad(),
+            file_path.display(),
+            e.to_string().bad());
+    }
+    // Ctrl-C while following is the normal way out, so only count it during the scan
+    let interrupted = stop.load(Ordering::Relaxed);
+    if report.stats.unscanned_files > 0 {
+        let reason = if interrupted {
+            "Interrupted".to_string()
+        } else {
+            format!("Deadline of {}s reached", cli.deadline.unwrap_or_default())
+        };
+        eprintln!("{} {}",
+            "⏹️".warn(),
+            format!("{}: {} of {} files were not scanned, results are partial",
+                reason,
+                report.stats.unscanned_files,
+                report.stats.total_files).warn());
     }
 
-    if stats.processed_files == 0 {
-        return Err(AppError::IoError(io::Error::new(
-            io::ErrorKind::Other,
+    if report.stats.processed_files == 0 && report.stats.unscanned_files == 0 {
+        return Err(AppError::IoError(io::Error::other(
             "❌ Could not process any files"
         )));
     }
 
-    if stats.total_errors > 0 {
-        println!("\n{}", "🔍 Errors Found:".cyan().bold());
-        println!("{}", "==============".cyan());
-        
-        for (file_name, error_lines) in &errors_by_file {
-            if !error_lines.is_empty() {
-                println!("\n{} {} ({} {})", 
-                    "📄".cyan(),
-                    file_name.bold(),
-                    error_lines.len(),
-                    if error_lines.len() == 1 { "error" } else { "errors" });
-
-                for entry in error_lines {
-                    println!("  {} {} - [{}] {}",
-                        "└─".cyan(),
-                        format!("Line {}", entry.line_number).yellow(),
-                        entry.format_timestamp().blue(),
-                        entry.content.red());
-                }
+    if cli.sort == SortOrder::Count || cli.top.is_some() {
+        // The sort is stable, so files with equal counts stay in path order
+        report.errors_by_file.sort_by_key(|(_, file_report)| Reverse(file_report.match_count));
+    }
+
+    // Taken before --top so that every file counts
+    let recurring = cli.top_errors.map_or_else(Vec::new, |limit| top_messages(&report.errors_by_file, limit));
+    let directories = if cli.group_by_dir { matches_by_directory(&report.errors_by_file, cli.dir_depth) } else { Vec::new() };
+
+    // The totals in the stats still cover every file
+    let mut hidden_files = 0;
+    if let Some(top) = cli.top {
+        hidden_files = report.errors_by_file.len().saturating_sub(top);
+        report.errors_by_file.truncate(top);
+    }
+
+    let split = cli.split_output.as_deref()
+        .map(|dir| write_split_reports(dir, &report.errors_by_file, cli.format, &style, labels))
+        .transpose()?;
+    let over_threshold = cli.fail_threshold.filter(|threshold| report.stats.total_errors > *threshold);
+
+    match cli.format {
+        _ if cli.quiet_if_clean && report.stats.total_errors == 0 => {}
+        _ if cli.summary_json => print_json_summary(&mut *out, &report.stats, report.duration)?,
+        _ if cli.metrics => print_metrics(&mut *out, &report.errors_by_file, &report.stats, report.duration)?,
+        OutputFormat::Text => {
+            let print_start = Instant::now();
+            if cli.quiet || cli.stream {
+                // Only the summary (streamed matches were already printed)
+            } else if cli.count {
+                print_counts(&mut *out, &report.errors_by_file, labels)?;
+            } else if let (Some(split), Some(dir)) = (&split, &cli.split_output) {
+                writeln!(out, "\n{} Wrote {} {} to {} (index: {})",
+                    "📂".info(),
+                    split.written,
+                    if split.written == 1 { "report" } else { "reports" },
+                    dir.display(),
+                    split.index.display())?;
+            } else if cli.first.is_some() || cli.last.is_some() {
+                print_time_slices(&mut *out, &report.errors_by_file, cli.first, cli.last, &style, labels)?;
+            } else if let Some(previous) = &previous {
+                print_comparison(&mut *out, &previous.compare(&report.errors_by_file), &style, labels)?;
+            } else {
+                print_errors(&mut *out, &report.errors_by_file, &style, labels)?;
+            }
+            if !cli.quiet {
+                print_top_messages(&mut *out, &recurring, &style, labels)?;
+            }
+            print_directory_counts(&mut *out, &directories, report.stats.total_errors, labels)?;
+            if hidden_files > 0 && !cli.quiet {
+                writeln!(out, "\n{} {} more {} with {} not shown (--top {})",
+                    "…".info(),
+                    hidden_files,
+                    if hidden_files == 1 { "file" } else { "files" },
+                    labels.many,
+                    report.errors_by_file.len())?;
+            }
+            print_summary(&mut *out, &report.stats, report.duration, labels)?;
+            if let Some(threshold) = over_threshold {
+                print_threshold_exceeded(&mut *out, report.stats.total_errors, threshold, labels)?;
+            }
+            if cli.rate {
+                print_rate(&mut *out, &report.stats, labels)?;
+            }
+            if cli.list_scanned {
+                print_scanned_files(&mut *out, &report.stats.scanned_files)?;
+            }
+            if cli.stats {
+                let timings = Timings {
+                    traversal: traversal_time,
+                    scanning: report.duration,
+                    printing: print_time + print_start.elapsed(),
+                };
+                print_throughput(&mut *out, &report.stats, &timings)?;
             }
         }
-    } else {
-        println!("\n{} {}", "✅".green(), "No errors found in processed files.".green());
+        _ if cli.quiet => {}
+        // The matches went to the split reports
+        OutputFormat::Json if split.is_some() => print_json_summary(&mut *out, &report.stats, report.duration)?,
+        OutputFormat::Json => print_json_report(&mut *out, &report.errors_by_file, &report.stats, report.duration)?,
+        OutputFormat::Csv => print_csv_report(&mut *out, &report.errors_by_file)?,
+        OutputFormat::Markdown => print_markdown_report(&mut *out, &report.errors_by_file, &report.stats, report.duration)?,
+        OutputFormat::Html => print_html_report(&mut *out, &report.errors_by_file, &report.stats, report.duration, &options)?,
+        // Already printed during the scan
+        OutputFormat::Ndjson => {}
+    }
+    out.flush()?;
+    if let Some(threshold) = over_threshold {
+        // The other formats have no summary to put it in
+        if cli.format != OutputFormat::Text || cli.summary_json || cli.metrics {
+            print_threshold_exceeded(&mut io::stderr(), report.stats.total_errors, threshold, labels)?;
+        }
     }
 
-    let duration = start_time.elapsed();
-    stats.print_summary(duration);
-    
-    Ok(())
-}
\ No newline at end of file
+    if cli.follow {
+        let follower = Follower::new(&log_dir_path, &collected.files, &options);
+        if decorated {
+            eprintln!("\n{} Following {} files for new matches (Ctrl-C to stop)...",
+                "👀".info(),
+                follower.len());
+        }
+        follow_files(&mut *out, follower, cli.notify.then(Notifier::new), &style, &stop)?;
+    }
+
+    if interrupted {
+        Ok(EXIT_INTERRUPTED)
+    } else if (cli.exit_on_match && report.stats.total_errors > 0) || over_threshold.is_some() {
+        Ok(EXIT_MATCHES)
+    } else {
+        Ok(EXIT_CLEAN)
+    }
+}
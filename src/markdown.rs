@@ -0,0 +1,109 @@
+use std::io::{self, Write};
+use std::time::Duration;
+use chrono::Local;
+use rustwatch::{FileReport, ScanStats};
+
+// A fence longer than any run of backticks in the block, so log lines
+// containing ``` can't end it early
+fn code_fence(lines: &[String]) -> String {
+    let longest_run = lines.iter()
+        .flat_map(|line| line.split(|c| c != '`'))
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    "`".repeat((longest_run + 1).max(3))
+}
+
+fn write_summary(out: &mut dyn Write, stats: &ScanStats, files_with_matches: usize, duration: Duration) -> io::Result<()> {
+    writeln!(out, "## Summary\n")?;
+    writeln!(out, "| Statistic | Value |")?;
+    writeln!(out, "| --- | ---: |")?;
+    writeln!(out, "| Scan time | {} ms |", duration.as_millis())?;
+    writeln!(out, "| Files scanned | {} |", stats.processed_files)?;
+    writeln!(out, "| Files with matches | {} |", files_with_matches)?;
+    writeln!(out, "| Total matches | {} |", stats.total_errors)?;
+    writeln!(out, "| Files skipped | {} |", stats.skipped_files)?;
+    writeln!(out, "| Paths excluded | {} |", stats.excluded_paths)?;
+    writeln!(out, "| Files truncated by --max-matches | {} |", stats.truncated_files)?;
+    writeln!(out, "| Files changed during scan | {} |", stats.changed_files)?;
+    writeln!(out, "| Large files encountered | {} |", stats.large_files)?;
+
+    if !stats.oversized_files.is_empty() {
+        writeln!(out, "\nSkipped for exceeding the size limit:\n")?;
+        for file_name in &stats.oversized_files {
+            writeln!(out, "- `{}`", file_name)?;
+        }
+    }
+    if !stats.timed_out_files.is_empty() {
+        writeln!(out, "\nTimed out (rerun with `--timeout 0` to include them):\n")?;
+        for file_name in &stats.timed_out_files {
+            writeln!(out, "- `{}`", file_name)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the report as a Markdown document: a summary table followed by a
+/// heading and a code block of matched lines per file.
+///
+/// Matched lines are prefixed `N:` and context lines `N-`, like grep.
+pub fn print_markdown_report(
+    out: &mut dyn Write,
+    errors_by_file: &[(String, FileReport)],
+    stats: &ScanStats,
+    duration: Duration,
+) -> io::Result<()> {
+    writeln!(out, "# RustWatch Report\n")?;
+    writeln!(out, "Generated {} by RustWatch {}.\n",
+        Local::now().format("%Y-%m-%d %H:%M:%S"),
+        env!("CARGO_PKG_VERSION"))?;
+    write_summary(out, stats, errors_by_file.len(), duration)?;
+
+    if errors_by_file.is_empty() {
+        writeln!(out, "\nNo matches found in processed files.")?;
+        return Ok(());
+    }
+
+    for (file_name, report) in errors_by_file {
+        writeln!(out, "\n## `{}`\n", file_name)?;
+        let mut truncation_note = if report.is_truncated() {
+            format!(" (showing first {})", report.entries.len())
+        } else {
+            String::new()
+        };
+        if report.changed_during_scan {
+            truncation_note.push_str(" — possibly incomplete, the file changed during scan");
+        }
+        writeln!(out, "{} {}{}\n",
+            report.match_count,
+            if report.match_count == 1 { "match" } else { "matches" },
+            truncation_note)?;
+
+        let mut lines = Vec::new();
+        for entry in &report.entries {
+            let first_before = entry.line_number - entry.before.len();
+            for (i, line) in entry.before.iter().enumerate() {
+                lines.push(format!("{}- {}", first_before + i, line));
+            }
+
+            let mut line = format!("{}: [{}] {}", entry.line_number, entry.format_timestamp(), entry.content);
+            if let Some(last) = entry.last_line_number.filter(|_| entry.occurrences > 1) {
+                line.push_str(&format!(" (×{}, last on line {})", entry.occurrences, last));
+            }
+            lines.push(line);
+
+            for (i, line) in entry.after.iter().enumerate() {
+                lines.push(format!("{}- {}", entry.end_line_number() + 1 + i, line));
+            }
+        }
+
+        let fence = code_fence(&lines);
+        writeln!(out, "{}text", fence)?;
+        for line in &lines {
+            writeln!(out, "{}", line)?;
+        }
+        writeln!(out, "{}", fence)?;
+    }
+
+    Ok(())
+}
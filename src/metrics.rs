@@ -0,0 +1,41 @@
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use rustwatch::{FileReport, ScanStats};
+
+// Label values may contain anything but backslashes, quotes and newlines
+fn escape_label(value: &str) -> String {
+    value.replace('\\', r"\\").replace('"', "\\\"").replace('\n', r"\n")
+}
+
+fn write_gauge(out: &mut dyn Write, name: &str, help: &str, value: impl std::fmt::Display) -> io::Result<()> {
+    writeln!(out, "# HELP {} {}", name, help)?;
+    writeln!(out, "# TYPE {} gauge", name)?;
+    writeln!(out, "{} {}", name, value)
+}
+
+/// Writes the scan results in the Prometheus text exposition format, for
+/// example for node_exporter's textfile collector.
+///
+/// Every value describes the latest scan, so all metrics are gauges.
+pub fn print_metrics(
+    out: &mut dyn Write,
+    errors_by_file: &[(String, FileReport)],
+    stats: &ScanStats,
+    duration: Duration,
+) -> io::Result<()> {
+    writeln!(out, "# HELP rustwatch_errors_total Matching lines found in each file by the last scan.")?;
+    writeln!(out, "# TYPE rustwatch_errors_total gauge")?;
+    for (file_name, report) in errors_by_file {
+        writeln!(out, "rustwatch_errors_total{{file=\"{}\"}} {}", escape_label(file_name), report.match_count)?;
+    }
+
+    write_gauge(out, "rustwatch_matches", "Matching lines found across all files.", stats.total_errors)?;
+    write_gauge(out, "rustwatch_files_total", "Files selected for scanning.", stats.total_files)?;
+    write_gauge(out, "rustwatch_files_scanned", "Files scanned successfully.", stats.processed_files)?;
+    write_gauge(out, "rustwatch_files_skipped", "Files that could not be scanned.", stats.skipped_files)?;
+    write_gauge(out, "rustwatch_files_with_matches", "Files with at least one matching line.", errors_by_file.len())?;
+    write_gauge(out, "rustwatch_scan_duration_seconds", "Wall-clock time the scan took.", duration.as_secs_f64())?;
+
+    let finished = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    write_gauge(out, "rustwatch_last_scan_timestamp_seconds", "When the scan finished, in seconds since the epoch.", finished.as_secs())
+}
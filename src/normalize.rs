@@ -0,0 +1,91 @@
+// Placeholders stand in for the parts of a message that vary between
+// otherwise identical errors
+const UUID_LEN: usize = 36;
+
+fn ends_token(bytes: &[u8], len: usize) -> bool {
+    bytes.get(len).is_none_or(|b| !b.is_ascii_alphanumeric())
+}
+
+// e.g. 123e4567-e89b-12d3-a456-426614174000
+fn uuid_len(bytes: &[u8]) -> Option<usize> {
+    let candidate = bytes.get(..UUID_LEN)?;
+    let well_formed = candidate.iter().enumerate().all(|(i, b)| match i {
+        8 | 13 | 18 | 23 => *b == b'-',
+        _ => b.is_ascii_hexdigit(),
+    });
+    (well_formed && ends_token(bytes, UUID_LEN)).then_some(UUID_LEN)
+}
+
+// Four dot-separated groups of one to three digits
+fn ipv4_len(bytes: &[u8]) -> Option<usize> {
+    let mut len = 0;
+    for group in 0..4 {
+        if group > 0 {
+            if bytes.get(len) != Some(&b'.') {
+                return None;
+            }
+            len += 1;
+        }
+        let digits = bytes[len..].iter().take_while(|b| b.is_ascii_digit()).count();
+        if !(1..=3).contains(&digits) {
+            return None;
+        }
+        len += digits;
+    }
+
+    // Reject longer dotted numbers such as version strings
+    let continues = bytes.get(len) == Some(&b'.') && bytes.get(len + 1).is_some_and(u8::is_ascii_digit);
+    (!continues && ends_token(bytes, len)).then_some(len)
+}
+
+// 0x-prefixed values, and hashes or ids of eight or more hex characters
+fn hex_len(bytes: &[u8]) -> Option<usize> {
+    if let Some(rest) = bytes.strip_prefix(b"0x") {
+        let digits = rest.iter().take_while(|b| b.is_ascii_hexdigit()).count();
+        return (digits > 0 && ends_token(bytes, digits + 2)).then_some(digits + 2);
+    }
+
+    let len = bytes.iter().take_while(|b| b.is_ascii_hexdigit()).count();
+    let candidate = &bytes[..len];
+    // Requiring both digits and letters keeps plain words and numbers out
+    let mixed = candidate.iter().any(u8::is_ascii_digit) && candidate.iter().any(u8::is_ascii_alphabetic);
+    (len >= 8 && mixed && ends_token(bytes, len)).then_some(len)
+}
+
+/// Replaces the variable parts of a log message (UUIDs, IPv4 addresses, hex
+/// ids and numbers) with placeholders, so that messages differing only in
+/// those parts normalize to the same string.
+///
+/// `"connection from 10.0.0.5 failed"` becomes `"connection from <ip> failed"`.
+pub fn normalize_message(line: &str) -> String {
+    let bytes = line.as_bytes();
+    let mut normalized = String::with_capacity(line.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let token_start = i == 0 || !bytes[i - 1].is_ascii_alphanumeric();
+        if token_start {
+            let rest = &bytes[i..];
+            let replacement = uuid_len(rest).map(|len| (len, "<uuid>"))
+                .or_else(|| ipv4_len(rest).map(|len| (len, "<ip>")))
+                .or_else(|| hex_len(rest).map(|len| (len, "<hex>")));
+            if let Some((len, placeholder)) = replacement {
+                normalized.push_str(placeholder);
+                i += len;
+                continue;
+            }
+        }
+
+        if bytes[i].is_ascii_digit() {
+            normalized.push_str("<num>");
+            i += bytes[i..].iter().take_while(|b| b.is_ascii_digit()).count();
+            continue;
+        }
+
+        let c = line[i..].chars().next().expect("index is on a char boundary");
+        normalized.push(c);
+        i += c.len_utf8();
+    }
+
+    normalized
+}
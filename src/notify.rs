@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use notify_rust::Notification;
+use rustwatch::Themed;
+
+// Within one window, a file gets at most NOTIFY_BURST individual
+// notifications; anything past that is rolled into a single summary
+const NOTIFY_WINDOW: Duration = Duration::from_secs(10);
+const NOTIFY_BURST: usize = 3;
+const MAX_BODY_CHARS: usize = 200;
+
+struct FileWindow {
+    started: Instant,
+    sent: usize,
+    suppressed: usize,
+}
+
+/// Desktop notifications for matches found in follow mode, debounced per file.
+pub struct Notifier {
+    windows: HashMap<String, FileWindow>,
+    disabled: bool,
+}
+
+impl Notifier {
+    pub fn new() -> Self {
+        Self {
+            windows: HashMap::new(),
+            disabled: false,
+        }
+    }
+
+    pub fn notify_match(&mut self, file_name: &str, content: &str) {
+        self.flush();
+
+        let window = self.windows.entry(file_name.to_string())
+            .or_insert_with(|| FileWindow {
+                started: Instant::now(),
+                sent: 0,
+                suppressed: 0,
+            });
+
+        if window.sent < NOTIFY_BURST {
+            window.sent += 1;
+            self.send(&format!("RustWatch: {}", file_name), &truncate(content));
+        } else {
+            window.suppressed += 1;
+        }
+    }
+
+    /// Closes expired windows, sending a summary for any matches they held back.
+    pub fn flush(&mut self) {
+        let now = Instant::now();
+        let mut summaries = Vec::new();
+        self.windows.retain(|file_name, window| {
+            if now.duration_since(window.started) < NOTIFY_WINDOW {
+                return true;
+            }
+            if window.suppressed > 0 {
+                summaries.push((file_name.clone(), window.suppressed));
+            }
+            false
+        });
+
+        for (file_name, count) in summaries {
+            let body = format!("{} new {} in {}", count, if count == 1 { "error" } else { "errors" }, file_name);
+            self.send("RustWatch", &body);
+        }
+    }
+
+    fn send(&mut self, summary: &str, body: &str) {
+        if self.disabled {
+            return;
+        }
+
+        if let Err(e) = Notification::new().summary(summary).body(body).show() {
+            // Without a notification daemon every attempt would fail, so warn once
+            eprintln!("{} Desktop notifications disabled: {}", "⚠️".warn(), e.to_string().bad());
+            self.disabled = true;
+        }
+    }
+}
+
+fn truncate(content: &str) -> String {
+    let content = content.trim();
+    if content.chars().count() <= MAX_BODY_CHARS {
+        return content.to_string();
+    }
+    let truncated: String = content.chars().take(MAX_BODY_CHARS - 1).collect();
+    format!("{}…", truncated)
+}
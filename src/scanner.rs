@@ -0,0 +1,1386 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Read, Seek, SeekFrom};
+use std::ops::{AddAssign, Range};
+use std::path::{Path, PathBuf};
+use std::slice;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use colored::*;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE};
+use encoding_rs_io::DecodeReaderBytesBuilder;
+use flate2::read::GzDecoder;
+use globset::GlobSet;
+use humansize::{format_size, BINARY};
+use indicatif::ProgressBar;
+use memmap2::Mmap;
+use serde::{Deserialize, Serialize};
+
+use crate::baseline::Baseline;
+use crate::bytes::{escape_bytes, BytePattern};
+use crate::checkpoint::Checkpoint;
+use crate::collect::{has_known_extension, open_file_is_text, PathStyle, SniffSettings};
+use crate::error::{AppError, Result};
+use crate::jsonl::{field_text, parse_object, JsonFieldMatch};
+use crate::normalize::normalize_message;
+use crate::state::ScanState;
+use crate::syslog::{split_priority, Priority};
+use crate::theme::Themed;
+use crate::timestamp::{deserialize_timestamp, format_timestamp, parse_line_timestamp, serialize_timestamp};
+use crate::wildcard::WildcardKeywords;
+
+/// Default upper bound on the size of a scanned file.
+pub const MAX_FILE_SIZE: u64 = 1024 * 1024 * 1024; // 1GB
+/// Files at least this large are memory-mapped when [`ScanOptions::use_mmap`] is set.
+pub const MMAP_THRESHOLD: u64 = 64 * 1024 * 1024; // 64MB
+/// Default per-file processing timeout.
+pub const OPERATION_TIMEOUT: Duration = Duration::from_secs(30);
+/// Default cap on the length of a single line; the rest is dropped.
+pub const MAX_LINE_LENGTH: usize = 1024 * 1024; // 1MB
+/// Largest buffer files are read through, used for files of this size and up.
+pub const READ_BUFFER_SIZE: usize = 128 * 1024; // 128KB
+/// Smallest buffer files are read through.
+pub const MIN_READ_BUFFER_SIZE: usize = 1024;
+/// Default number of retries for transient errors opening a file.
+pub const DEFAULT_RETRIES: u32 = 2;
+
+// Wait before the first retry, doubled for each one after it
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+// Appended to lines cut short at max_line_length
+const TRUNCATION_MARKER: &str = " [… line truncated]";
+
+/// Settings that control which files are collected and how each one is scanned.
+#[derive(Clone, Debug)]
+pub struct ScanOptions {
+    /// A line matching any of these keywords is reported
+    pub keywords: Vec<String>,
+    /// When this or `any_of` is set, lines are matched by these terms instead
+    /// of `keywords`: a line must contain every term here and, if `any_of`
+    /// isn't empty, at least one term there
+    pub all_of: Vec<String>,
+    /// See `all_of`
+    pub any_of: Vec<String>,
+    /// When set, lines are matched by a word fitting one of these wildcard
+    /// patterns instead of by `keywords`
+    pub wildcard_keywords: Option<WildcardKeywords>,
+    /// When not empty, lines are matched by containing one of these byte
+    /// sequences instead of by `keywords`, and are read and reported as
+    /// rendered by [`escape_bytes`](crate::escape_bytes)
+    pub byte_patterns: Vec<BytePattern>,
+    /// Match keywords exactly instead of ignoring case
+    pub case_sensitive: bool,
+    /// Only match keywords that appear as whole words
+    pub whole_word: bool,
+    /// Report the lines that don't match instead of the ones that do
+    pub invert: bool,
+    /// Lines of context captured before each match
+    pub before_context: usize,
+    /// Lines of context captured after each match
+    pub after_context: usize,
+    /// Drop matches timestamped before this time
+    pub since: Option<SystemTime>,
+    /// Drop matches timestamped after this time
+    pub until: Option<SystemTime>,
+    /// Keep matches without a parseable timestamp when a time range is set
+    pub include_untimed: bool,
+    /// Stop collecting entries for a file after this many matches
+    pub max_matches: Option<usize>,
+    /// Per-file processing timeout, or `None` for no limit
+    pub timeout: Option<Duration>,
+    /// How many times opening and first reading a file is retried after a
+    /// transient error, such as a stale NFS handle, with exponential backoff
+    pub retries: u32,
+    /// Files not yet started once this passes are left unscanned, so the
+    /// whole scan ends soon after
+    pub deadline: Option<Instant>,
+    /// Once this is set, files not yet started are left unscanned, e.g. to
+    /// stop on Ctrl-C
+    pub stop: Option<Arc<AtomicBool>>,
+    /// Matches that this baseline suppresses are counted but not reported
+    pub baseline: Option<Baseline>,
+    /// Lines that match but also contain one of these patterns are treated
+    /// as not matching at all
+    pub excluded_lines: Option<Baseline>,
+    /// Files larger than this many bytes are skipped
+    pub max_size: u64,
+    /// Files smaller than this many bytes are left out when collecting
+    pub min_size: u64,
+    /// Also match lines at or above this log level
+    pub min_level: Option<Severity>,
+    /// Lines that are JSON objects match when any of these fields has the
+    /// given value, instead of by keyword; other lines still match by keyword
+    pub json_fields: Vec<JsonFieldMatch>,
+    /// Report this field of matched JSON lines instead of the whole line
+    pub json_display: Option<String>,
+    /// Take the level of lines with a syslog `<PRI>` prefix from the
+    /// priority; with `min_level` set, such lines match by priority alone
+    pub syslog: bool,
+    /// With `syslog`, only report prefixed lines from these facility codes
+    /// (see [`facility_code`](crate::facility_code)); empty allows all
+    pub facilities: Vec<u8>,
+    /// Files and directories matching these globs are not collected
+    pub excludes: Option<GlobSet>,
+    /// How many directory levels below the root to descend, or `None` for no limit
+    pub max_depth: Option<usize>,
+    /// Stop collecting once this many files were found, or `None` for no limit.
+    /// Files without a known text extension count as found before their
+    /// content is checked, so some may turn out not to be text.
+    pub max_files: Option<usize>,
+    /// Leave out files last modified before this time when collecting
+    pub modified_after: Option<SystemTime>,
+    /// Descend into symlinked directories instead of skipping them
+    pub follow_symlinks: bool,
+    /// Skip files and directories matched by `.gitignore`, `.ignore` and the
+    /// global git excludes file
+    pub respect_ignore_files: bool,
+    /// File extensions to treat as text on top of the built-in list, in
+    /// lowercase and without the leading dot
+    pub extra_extensions: Vec<String>,
+    /// Scan every file found instead of only those that look like text
+    pub all_files: bool,
+    /// How files without a known extension are judged to be text
+    pub sniff: SniffSettings,
+    /// At 1 or more, say on stderr why each skipped file was left out; at 2
+    /// or more, also name each directory as it is entered
+    pub verbosity: u8,
+    /// A spinner whose message shows how many files and directories have
+    /// been found so far while collecting
+    pub collect_progress: Option<ProgressBar>,
+    /// Resume each file where the last scan with this state stopped, and
+    /// record where this one stops; `None` scans files in full
+    pub incremental: Option<Arc<ScanState>>,
+    /// Skip the files this checkpoint has as finished, reporting their saved
+    /// results instead, and record each file finished now
+    pub checkpoint: Option<Arc<Checkpoint>>,
+    /// Longer lines are cut to this many bytes and marked as truncated;
+    /// only the part kept is matched
+    pub max_line_length: usize,
+    /// Decode files with this encoding when they have no byte order mark;
+    /// `None` reads them as UTF-8
+    pub encoding: Option<&'static Encoding>,
+    /// Read files through a buffer of this many bytes; `None` sizes it to
+    /// each file, between [`MIN_READ_BUFFER_SIZE`] and [`READ_BUFFER_SIZE`]
+    pub buffer_size: Option<usize>,
+    /// Memory-map plain files of at least [`MMAP_THRESHOLD`] bytes instead of
+    /// reading them through a buffer
+    pub use_mmap: bool,
+    /// Count matches per minute of their timestamps in
+    /// [`FileReport::matches_per_minute`]
+    pub per_minute: bool,
+    /// Record every file and what became of it in [`ScanStats::scanned_files`](crate::ScanStats::scanned_files)
+    pub list_scanned: bool,
+    /// Collapse identical matched lines within a file into a single entry
+    pub dedupe: bool,
+    /// Like `dedupe`, but lines that only differ in numbers, UUIDs, IPs or
+    /// hex ids are collapsed as well, keeping the first as an example
+    pub group_similar: bool,
+    /// Attach the lines that continue a match, such as the frames of a stack
+    /// trace, to its entry instead of scanning them on their own
+    pub merge_continuation: Option<ContinuationStyle>,
+    /// How file paths are shown in results; doesn't affect matching
+    pub path_style: PathStyle,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self::new(vec!["error".to_string()])
+    }
+}
+
+impl ScanOptions {
+    /// Creates options that search for `keywords` with every other setting at its default.
+    pub fn new(keywords: Vec<String>) -> Self {
+        Self {
+            keywords,
+            all_of: Vec::new(),
+            any_of: Vec::new(),
+            wildcard_keywords: None,
+            byte_patterns: Vec::new(),
+            case_sensitive: false,
+            whole_word: false,
+            invert: false,
+            before_context: 0,
+            after_context: 0,
+            since: None,
+            until: None,
+            include_untimed: false,
+            max_matches: None,
+            timeout: Some(OPERATION_TIMEOUT),
+            retries: DEFAULT_RETRIES,
+            deadline: None,
+            stop: None,
+            baseline: None,
+            excluded_lines: None,
+            max_size: MAX_FILE_SIZE,
+            min_size: 0,
+            min_level: None,
+            json_fields: Vec::new(),
+            json_display: None,
+            syslog: false,
+            facilities: Vec::new(),
+            excludes: None,
+            max_depth: None,
+            max_files: None,
+            modified_after: None,
+            follow_symlinks: false,
+            respect_ignore_files: false,
+            extra_extensions: Vec::new(),
+            all_files: false,
+            sniff: SniffSettings::default(),
+            verbosity: 0,
+            collect_progress: None,
+            incremental: None,
+            checkpoint: None,
+            max_line_length: MAX_LINE_LENGTH,
+            encoding: None,
+            buffer_size: None,
+            use_mmap: false,
+            per_minute: false,
+            list_scanned: false,
+            dedupe: false,
+            group_similar: false,
+            merge_continuation: None,
+            path_style: PathStyle::default(),
+        }
+    }
+
+    /// Whether the scan should stop starting new files, because the deadline
+    /// passed or `stop` was set.
+    pub fn should_stop(&self) -> bool {
+        self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+            || self.stop.as_ref().is_some_and(|stop| stop.load(Ordering::Relaxed))
+    }
+
+    /// Whether a matching line is known noise according to the baseline.
+    pub fn is_suppressed(&self, line: &str) -> bool {
+        self.baseline.as_ref().is_some_and(|baseline| baseline.suppresses(line))
+    }
+
+    /// Whether a line is left out by one of the `excluded_lines` patterns.
+    pub fn is_excluded_line(&self, line: &str) -> bool {
+        self.excluded_lines.as_ref().is_some_and(|excluded| excluded.suppresses(line))
+    }
+
+    /// Returns whether a line should be reported, by keyword or by log level,
+    /// or by its fields if it is a JSON object and `json_fields` are set.
+    pub fn matches_line(&self, line: &str) -> bool {
+        let priority = self.priority(line);
+        // Other facilities are left out entirely, even with invert
+        if priority.is_some_and(|priority| !self.facilities.is_empty() && !self.facilities.contains(&priority.facility)) {
+            return false;
+        }
+
+        let object = (!self.json_fields.is_empty()).then(|| parse_object(line)).flatten();
+        let matched = if let Some(object) = object {
+            self.json_fields.iter().any(|field| field.matches(&object, self.case_sensitive))
+        } else if let (Some(priority), Some(min)) = (priority, self.min_level) {
+            priority.level() >= min
+        } else {
+            self.is_match(line)
+                || self.min_level.is_some_and(|min| self.line_severity(line).is_some_and(|level| level >= min))
+        };
+        matched != self.invert
+    }
+
+    /// The keyword (or `all_of`/`any_of` terms, or JSON field condition) that
+    /// made a line match, or `None` for lines reported by level alone or
+    /// with `invert`.
+    pub fn matched_pattern(&self, line: &str) -> Option<String> {
+        if self.invert || (self.min_level.is_some() && self.priority(line).is_some()) {
+            return None;
+        }
+        if let Some(object) = (!self.json_fields.is_empty()).then(|| parse_object(line)).flatten() {
+            return self.json_fields.iter()
+                .find(|field| field.matches(&object, self.case_sensitive))
+                .map(|field| format!("{}={}", field.field, field.value));
+        }
+
+        if let Some(wildcards) = &self.wildcard_keywords {
+            return wildcards.matching_pattern(line).map(str::to_string);
+        }
+        if !self.byte_patterns.is_empty() {
+            return self.byte_patterns.iter().find(|pattern| pattern.is_found_in(line)).map(BytePattern::to_string);
+        }
+        let matches_term = |term: &&String| self.contains_any(line, slice::from_ref(*term));
+        if self.all_of.is_empty() && self.any_of.is_empty() {
+            return self.keywords.iter().find(matches_term).cloned();
+        }
+        if !self.is_match(line) {
+            return None;
+        }
+        // All of the required terms, then the first alternative found
+        let terms: Vec<&str> = self.all_of.iter()
+            .chain(self.any_of.iter().find(matches_term))
+            .map(String::as_str)
+            .collect();
+        Some(terms.join(","))
+    }
+
+    /// The log level of a line: its syslog priority when `syslog` is set and
+    /// the line has one, otherwise the first level name in it.
+    pub fn line_severity(&self, line: &str) -> Option<Severity> {
+        self.priority(line)
+            .map(Priority::level)
+            .or_else(|| detect_severity(line))
+    }
+
+    fn priority(&self, line: &str) -> Option<Priority> {
+        self.syslog.then(|| split_priority(line)).flatten().map(|(priority, _)| priority)
+    }
+
+    // The timestamp of a syslog line follows its <PRI> prefix
+    fn line_timestamp(&self, line: &str) -> Option<SystemTime> {
+        let line = self.syslog.then(|| split_priority(line)).flatten().map_or(line, |(_, rest)| rest);
+        parse_line_timestamp(line)
+    }
+
+    /// The size of the buffer a file of `len` bytes is read through.
+    ///
+    /// Without a `buffer_size`, small files get a buffer just large enough to
+    /// hold them, so that scanning many of them in parallel doesn't allocate
+    /// [`READ_BUFFER_SIZE`] for each.
+    pub fn read_buffer_size(&self, len: u64) -> usize {
+        self.buffer_size.unwrap_or_else(|| {
+            let len = usize::try_from(len).unwrap_or(usize::MAX);
+            len.saturating_add(1).next_power_of_two().clamp(MIN_READ_BUFFER_SIZE, READ_BUFFER_SIZE)
+        })
+    }
+
+    /// Whether a line continues the one before it according to
+    /// `merge_continuation`. Blank lines never do.
+    pub fn is_continuation(&self, line: &str) -> bool {
+        match self.merge_continuation {
+            _ if line.trim().is_empty() => false,
+            Some(ContinuationStyle::Indented) => line.starts_with([' ', '\t']),
+            Some(ContinuationStyle::Untimed) => self.line_timestamp(line).is_none(),
+            None => false,
+        }
+    }
+
+    /// What a matched line is reported as: the `json_display` field for JSON
+    /// lines that have it, otherwise the line itself.
+    pub fn display_content<'l>(&self, line: &'l str) -> Cow<'l, str> {
+        let Some(field) = &self.json_display else {
+            return Cow::Borrowed(line);
+        };
+        parse_object(line)
+            .and_then(|object| field_text(&object, field).map(Cow::into_owned))
+            .map_or(Cow::Borrowed(line), Cow::Owned)
+    }
+
+    /// Decides whether a line is reported, returning its parsed timestamp if so.
+    ///
+    /// A line is reported when it matches and its timestamp falls within the
+    /// configured time range.
+    pub fn match_line(&self, line: &str) -> Option<Option<SystemTime>> {
+        // Only parse timestamps for lines that are going to be reported
+        (self.matches_line(line) && !self.is_excluded_line(line))
+            .then(|| self.line_timestamp(line))
+            .filter(|timestamp| self.accepts_timestamp(*timestamp))
+    }
+
+    /// Byte ranges of the keyword occurrences in a line, sorted and merged,
+    /// for highlighting. Level-only (`min_level`) matches have no ranges.
+    pub fn keyword_ranges(&self, line: &str) -> Vec<Range<usize>> {
+        if let Some(wildcards) = &self.wildcard_keywords {
+            return wildcards.word_ranges(line);
+        }
+        if !self.byte_patterns.is_empty() {
+            let ranges = self.byte_patterns.iter().flat_map(|pattern| pattern.find_in(line)).collect();
+            return merge_ranges(ranges);
+        }
+        let lowered;
+        let haystack = if self.case_sensitive {
+            line
+        } else {
+            lowered = line.to_lowercase();
+            // Offsets only carry over if lowercasing kept every byte in place
+            if lowered.len() != line.len() {
+                return Vec::new();
+            }
+            &lowered
+        };
+
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for keyword in self.match_terms().filter(|keyword| !keyword.is_empty()) {
+            let keyword = if self.case_sensitive { keyword.clone() } else { keyword.to_lowercase() };
+            ranges.extend(haystack.match_indices(keyword.as_str())
+                .map(|(start, _)| start..start + keyword.len())
+                .filter(|range| !self.whole_word || is_word_at(haystack, range.start, range.end)));
+        }
+        merge_ranges(ranges)
+    }
+
+    fn accepts_timestamp(&self, timestamp: Option<SystemTime>) -> bool {
+        if self.since.is_none() && self.until.is_none() {
+            return true;
+        }
+
+        match timestamp {
+            Some(ts) => self.since.is_none_or(|since| ts >= since)
+                && self.until.is_none_or(|until| ts <= until),
+            None => self.include_untimed,
+        }
+    }
+
+    // The terms a line is matched by: `all_of` and `any_of` if set, otherwise the keywords
+    fn match_terms(&self) -> impl Iterator<Item = &String> {
+        let (first, second): (&[String], &[String]) = if self.all_of.is_empty() && self.any_of.is_empty() {
+            (&self.keywords, &[])
+        } else {
+            (&self.all_of, &self.any_of)
+        };
+        first.iter().chain(second)
+    }
+
+    fn is_match(&self, line: &str) -> bool {
+        if let Some(wildcards) = &self.wildcard_keywords {
+            return wildcards.is_match(line);
+        }
+        if !self.byte_patterns.is_empty() {
+            return self.byte_patterns.iter().any(|pattern| pattern.is_found_in(line));
+        }
+        if self.all_of.is_empty() && self.any_of.is_empty() {
+            return self.contains_any(line, &self.keywords);
+        }
+        self.all_of.iter().all(|term| self.contains_any(line, slice::from_ref(term)))
+            && (self.any_of.is_empty() || self.contains_any(line, &self.any_of))
+    }
+
+    fn contains_any(&self, line: &str, keywords: &[String]) -> bool {
+        if self.case_sensitive {
+            return keywords.iter().any(|keyword| self.contains_keyword(line, keyword));
+        }
+
+        // ASCII keywords can be found without lowercasing (and copying) every line
+        if keywords.iter().all(|keyword| keyword.is_ascii()) {
+            return keywords.iter().any(|keyword| self.contains_ascii_keyword(line, keyword));
+        }
+
+        let line = line.to_lowercase();
+        keywords.iter().any(|keyword| self.contains_keyword(&line, &keyword.to_lowercase()))
+    }
+
+    fn contains_ascii_keyword(&self, line: &str, keyword: &str) -> bool {
+        let (haystack, needle) = (line.as_bytes(), keyword.as_bytes());
+        let Some(&first) = needle.first() else {
+            return !self.whole_word;
+        };
+
+        memchr::memchr2_iter(first.to_ascii_lowercase(), first.to_ascii_uppercase(), haystack)
+            .any(|start| {
+                let end = start + needle.len();
+                haystack.get(start..end).is_some_and(|candidate| candidate.eq_ignore_ascii_case(needle))
+                    && (!self.whole_word || is_word_at(line, start, end))
+            })
+    }
+
+    fn contains_keyword(&self, line: &str, keyword: &str) -> bool {
+        if self.whole_word {
+            contains_word(line, keyword)
+        } else {
+            line.contains(keyword)
+        }
+    }
+}
+
+// Hyphens and underscores are treated as part of a word so that
+// identifiers like "no-error" or "error_count" don't count as a match
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+// Sorts ranges and merges those that overlap or touch
+fn merge_ranges(mut ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+fn contains_word(line: &str, keyword: &str) -> bool {
+    if keyword.is_empty() {
+        return false;
+    }
+
+    line.match_indices(keyword).any(|(start, _)| is_word_at(line, start, start + keyword.len()))
+}
+
+// Whether line[start..end] is not part of a longer word
+fn is_word_at(line: &str, start: usize, end: usize) -> bool {
+    let boundary_before = line[..start].chars().next_back().is_none_or(|c| !is_word_char(c));
+    let boundary_after = line[end..].chars().next().is_none_or(|c| !is_word_char(c));
+    boundary_before && boundary_after
+}
+
+/// Log level of a matched line, ordered from least to most severe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Critical,
+}
+
+impl Severity {
+    fn from_token(token: &str) -> Option<Self> {
+        match token.to_uppercase().as_str() {
+            "TRACE" | "DEBUG" => Some(Severity::Debug),
+            "INFO" | "NOTICE" => Some(Severity::Info),
+            "WARN" | "WARNING" => Some(Severity::Warn),
+            "ERR" | "ERROR" => Some(Severity::Error),
+            "CRIT" | "CRITICAL" | "FATAL" | "ALERT" | "EMERG" | "PANIC" => Some(Severity::Critical),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        Severity::from_token(value).ok_or_else(|| format!("unknown log level '{}'", value))
+    }
+}
+
+/// How lines continuing a multi-line event, such as a stack trace, are told
+/// apart from the lines starting one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContinuationStyle {
+    /// Lines without a timestamp of their own continue the one before
+    Untimed,
+    /// Lines starting with a space or tab continue the one before
+    Indented,
+}
+
+impl FromStr for ContinuationStyle {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "untimed" => Ok(ContinuationStyle::Untimed),
+            "indented" => Ok(ContinuationStyle::Indented),
+            _ => Err(format!("unknown continuation style '{}'", value)),
+        }
+    }
+}
+
+/// Matches counted by log level.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LevelCounts {
+    pub critical: usize,
+    pub error: usize,
+    pub warn: usize,
+    pub info: usize,
+    pub debug: usize,
+    /// Matches without a recognizable level
+    pub unknown: usize,
+}
+
+impl LevelCounts {
+    pub fn add(&mut self, level: Option<Severity>) {
+        match level {
+            Some(Severity::Critical) => self.critical += 1,
+            Some(Severity::Error) => self.error += 1,
+            Some(Severity::Warn) => self.warn += 1,
+            Some(Severity::Info) => self.info += 1,
+            Some(Severity::Debug) => self.debug += 1,
+            None => self.unknown += 1,
+        }
+    }
+}
+
+impl AddAssign for LevelCounts {
+    fn add_assign(&mut self, other: Self) {
+        self.critical += other.critical;
+        self.error += other.error;
+        self.warn += other.warn;
+        self.info += other.info;
+        self.debug += other.debug;
+        self.unknown += other.unknown;
+    }
+}
+
+/// Returns the level named by the first log-level token in a line, if any.
+pub fn detect_severity(line: &str) -> Option<Severity> {
+    line.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .find_map(Severity::from_token)
+}
+
+/// A single matched line.
+///
+/// Entries serialize to the JSON report format and deserialize back from it,
+/// so a saved report can be loaded again.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub line_number: usize,
+    pub content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+    /// Parsed from the line itself, falling back to the file's mtime
+    #[serde(default, serialize_with = "serialize_timestamp", deserialize_with = "deserialize_timestamp")]
+    pub timestamp: Option<SystemTime>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub before: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub after: Vec<String>,
+    /// How many identical (or similar) lines this entry stands for when grouping
+    #[serde(default = "one", skip_serializing_if = "is_one")]
+    pub occurrences: usize,
+    /// Line number of the last line in the group, when there was more than one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_line_number: Option<usize>,
+    /// What the line matched, see [`ScanOptions::matched_pattern`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matched_pattern: Option<String>,
+    /// Where the entry is in the systemd journal, for matches read from it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    /// Lines merged into `content` after the matched one, see
+    /// [`ScanOptions::merge_continuation`]
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub continuation_lines: usize,
+}
+
+fn is_zero(count: &usize) -> bool {
+    *count == 0
+}
+
+fn is_one(count: &usize) -> bool {
+    *count == 1
+}
+
+fn one() -> usize {
+    1
+}
+
+impl LogEntry {
+    pub fn format_timestamp(&self) -> String {
+        self.timestamp
+            .map(format_timestamp)
+            .unwrap_or_else(|| "Unknown time".to_string())
+    }
+
+    /// The last line of the entry's content, after any merged continuation lines.
+    pub fn end_line_number(&self) -> usize {
+        self.line_number + self.continuation_lines
+    }
+}
+
+/// The outcome of scanning a single file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FileReport {
+    pub entries: Vec<LogEntry>,
+    /// Total matches in the file, which exceeds the entries' occurrences when `max_matches` applies
+    pub match_count: usize,
+    /// Matches left out because the baseline suppresses them
+    pub suppressed_count: usize,
+    /// `match_count` broken down by the level of each matching line
+    pub matches_by_level: LevelCounts,
+    /// Matches without a timestamp of their own
+    pub untimed_count: usize,
+    /// With [`ScanOptions::per_minute`], the matches with a timestamp
+    /// counted by the minute since the Unix epoch they fall in
+    pub matches_per_minute: BTreeMap<u64, usize>,
+    /// The file's size or modification time changed while it was read, e.g.
+    /// because it was rotated or truncated, so the results may be incomplete
+    pub changed_during_scan: bool,
+    /// Bytes of the file read by this scan, before any decompression
+    pub bytes_read: u64,
+    /// Lines read by this scan, whether they matched or not
+    pub lines_read: usize,
+    /// The file relative to the scan root (when it is under it) regardless
+    /// of the path style, set once the report is part of a scan
+    #[serde(skip)]
+    pub relative_path: PathBuf,
+}
+
+impl FileReport {
+    pub fn is_truncated(&self) -> bool {
+        self.match_count > self.entries.iter().map(|entry| entry.occurrences).sum()
+    }
+}
+
+pub(crate) fn is_gzip_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gz"))
+}
+
+fn check_timeout(start_time: Instant, options: &ScanOptions, file_path: &Path) -> Result<()> {
+    if let Some(timeout) = options.timeout {
+        if start_time.elapsed() > timeout {
+            return Err(AppError::TimeoutError(
+                format!("Processing of file {:?} timed out after {} seconds",
+                    file_path, timeout.as_secs())
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Cuts `line` to at most `max_len` bytes on a character boundary,
+/// returning whether anything was cut.
+pub(crate) fn truncate_line(line: &str, max_len: usize) -> (&str, bool) {
+    if line.len() <= max_len {
+        return (line, false);
+    }
+    let mut end = max_len;
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    (&line[..end], true)
+}
+
+/// The line as kept in a report, marked if it was cut short.
+pub(crate) fn stored_line(line: &str, truncated: bool) -> String {
+    if truncated {
+        format!("{}{}", line, TRUNCATION_MARKER)
+    } else {
+        line.to_string()
+    }
+}
+
+// Turns the lines of a file into report entries, applying the match
+// options, context and the --max-matches cap
+pub(crate) struct EntryCollector<'a> {
+    options: &'a ScanOptions,
+    pub(crate) entries: Vec<LogEntry>,
+    match_count: usize,
+    suppressed_count: usize,
+    matches_by_level: LevelCounts,
+    untimed_count: usize,
+    matches_per_minute: BTreeMap<u64, usize>,
+    // Context lines that may precede the next match, and how many lines
+    // still belong to the trailing context of the previous match
+    recent_lines: VecDeque<String>,
+    after_remaining: usize,
+    // Whether continuation lines still belong to the last matched line, and
+    // the entry they are added to, if it got one of its own
+    continuing: bool,
+    continued_entry: Option<usize>,
+    file_modified: Option<SystemTime>,
+    // Index of the entry for each distinct line (or pattern), when grouping
+    seen: HashMap<String, usize>,
+}
+
+impl<'a> EntryCollector<'a> {
+    pub(crate) fn new(options: &'a ScanOptions, file_modified: Option<SystemTime>) -> Self {
+        Self {
+            options,
+            entries: Vec::new(),
+            match_count: 0,
+            suppressed_count: 0,
+            matches_by_level: LevelCounts::default(),
+            untimed_count: 0,
+            matches_per_minute: BTreeMap::new(),
+            recent_lines: VecDeque::with_capacity(options.before_context),
+            after_remaining: 0,
+            continuing: false,
+            continued_entry: None,
+            file_modified,
+            seen: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn push_line(&mut self, line_number: usize, line: &str) {
+        if !self.options.byte_patterns.is_empty() {
+            return self.push_raw_line(line_number, line.as_bytes());
+        }
+        let (line, truncated) = truncate_line(line, self.options.max_line_length);
+        self.push_read_line(line_number, line, truncated);
+    }
+
+    // With byte patterns, lines are matched and reported as escaped text,
+    // whether or not they are valid UTF-8
+    pub(crate) fn push_raw_line(&mut self, line_number: usize, line: &[u8]) {
+        let kept = &line[..line.len().min(self.options.max_line_length)];
+        self.push_escaped_line(line_number, kept, kept.len() < line.len());
+    }
+
+    fn push_escaped_line(&mut self, line_number: usize, line: &[u8], truncated: bool) {
+        self.push_read_line(line_number, &escape_bytes(line), truncated);
+    }
+
+    // For lines the reader already cut short
+    fn push_read_line(&mut self, line_number: usize, line: &str, truncated: bool) {
+        if self.continuing && self.options.is_continuation(line) {
+            if let Some(entry) = self.continued_entry.map(|index| &mut self.entries[index]) {
+                entry.continuation_lines += 1;
+                // Past the line length cap the event stops growing, so a
+                // runaway trace can't take up unbounded memory
+                if entry.content.len() < self.options.max_line_length {
+                    entry.content.push('\n');
+                    entry.content.push_str(&stored_line(line, truncated));
+                }
+            }
+            return;
+        }
+        let mut matched_timestamp = self.options.match_line(line);
+        // Even without an entry (repeats, suppressed matches and those past
+        // --max-matches) the rest of the event isn't scanned on its own
+        self.continuing = matched_timestamp.is_some() && self.options.merge_continuation.is_some();
+        self.continued_entry = None;
+        // Suppressed matches are treated like any other line, so they can
+        // still show up as context
+        if matched_timestamp.is_some() && self.options.is_suppressed(line) {
+            self.suppressed_count += 1;
+            matched_timestamp = None;
+        }
+        if let Some(timestamp) = matched_timestamp {
+            self.match_count += 1;
+            self.matches_by_level.add(self.options.line_severity(line));
+            match timestamp {
+                Some(timestamp) if self.options.per_minute => {
+                    if let Ok(since_epoch) = timestamp.duration_since(UNIX_EPOCH) {
+                        *self.matches_per_minute.entry(since_epoch.as_secs() / 60).or_insert(0) += 1;
+                    }
+                }
+                Some(_) => {}
+                None => self.untimed_count += 1,
+            }
+
+            // Repeats of an earlier line only bump its count, even past --max-matches
+            if let Some(&index) = self.group_key(line).and_then(|key| self.seen.get(key.as_ref())) {
+                let entry = &mut self.entries[index];
+                entry.occurrences += 1;
+                entry.last_line_number = Some(line_number);
+                self.break_context();
+                return;
+            }
+        }
+
+        // Past --max-matches, keep counting but stop collecting entries
+        let kept_timestamp = matched_timestamp
+            .filter(|_| self.options.max_matches.is_none_or(|max| self.entries.len() < max));
+
+        if let Some(timestamp) = kept_timestamp {
+            if let Some(key) = self.group_key(line) {
+                self.seen.insert(key.into_owned(), self.entries.len());
+            }
+            self.entries.push(LogEntry {
+                line_number,
+                timestamp: timestamp.or(self.file_modified),
+                severity: self.options.line_severity(line),
+                content: stored_line(&self.options.display_content(line), truncated),
+                before: self.recent_lines.drain(..).collect(),
+                after: Vec::new(),
+                occurrences: 1,
+                last_line_number: None,
+                matched_pattern: self.options.matched_pattern(line),
+                cursor: None,
+                continuation_lines: 0,
+            });
+            self.after_remaining = self.options.after_context;
+            self.continued_entry = Some(self.entries.len() - 1);
+        } else if self.after_remaining > 0 {
+            if let Some(entry) = self.entries.last_mut() {
+                entry.after.push(stored_line(line, truncated));
+            }
+            self.after_remaining -= 1;
+        } else if self.options.before_context > 0 {
+            if self.recent_lines.len() == self.options.before_context {
+                self.recent_lines.pop_front();
+            }
+            self.recent_lines.push_back(stored_line(line, truncated));
+        }
+    }
+
+    // The key identical (or, with --group-similar, similar) lines share
+    fn group_key<'l>(&self, line: &'l str) -> Option<Cow<'l, str>> {
+        if self.options.group_similar {
+            Some(Cow::Owned(normalize_message(line)))
+        } else if self.options.dedupe {
+            Some(Cow::Borrowed(line))
+        } else {
+            None
+        }
+    }
+
+    // Ends any pending run of context lines, e.g. at a line that can't be
+    // decoded or a repeat that only bumps an earlier match's count
+    fn break_context(&mut self) {
+        self.recent_lines.clear();
+        self.after_remaining = 0;
+    }
+
+    pub(crate) fn finish(self) -> FileReport {
+        FileReport {
+            entries: self.entries,
+            match_count: self.match_count,
+            suppressed_count: self.suppressed_count,
+            matches_by_level: self.matches_by_level,
+            untimed_count: self.untimed_count,
+            matches_per_minute: self.matches_per_minute,
+            changed_during_scan: false,
+            bytes_read: 0,
+            lines_read: 0,
+            relative_path: PathBuf::new(),
+        }
+    }
+}
+
+fn validate_file_size(size: u64, limit: u64, path: &Path) -> Result<()> {
+    if size > limit {
+        return Err(AppError::FileSizeError(
+            format!("File {:?} exceeds maximum size limit of {}",
+                path, format_size(limit, BINARY))
+        ));
+    }
+    Ok(())
+}
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+
+/// Scans one log file (plain or gzip-compressed) for lines matching `options`.
+///
+/// Fails with [`AppError::FileSizeError`] when the file exceeds
+/// `options.max_size` and with [`AppError::TimeoutError`] when it takes
+/// longer than `options.timeout`.
+pub fn process_log_file(file_path: &Path, options: &ScanOptions) -> Result<FileReport> {
+    let file = open_log_file(file_path, options)?;
+    scan_open_file(file, file_path, options)
+}
+
+// Like process_log_file, but first sniffs the open file and returns `None`
+// if it doesn't look like text
+pub(crate) fn process_unsniffed_file(file_path: &Path, options: &ScanOptions) -> Result<Option<FileReport>> {
+    let file = open_log_file(file_path, options)?;
+    let is_text = with_retries(options, file_path, || open_file_is_text(&file, file_path, &options.sniff)).map_err(|e| AppError::FileProcessingError {
+        path: file_path.to_path_buf(),
+        error: e.to_string(),
+    })?;
+    if !is_text {
+        return Ok(None);
+    }
+    scan_open_file(file, file_path, options).map(Some)
+}
+
+// Errors that network filesystems (NFS, CIFS) give for a momentary outage
+fn is_transient(error: &io::Error) -> bool {
+    matches!(error.kind(),
+        io::ErrorKind::StaleNetworkFileHandle
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::Interrupted
+            | io::ErrorKind::WouldBlock
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::HostUnreachable
+            | io::ErrorKind::NetworkUnreachable)
+}
+
+// Runs `operation` until it succeeds, fails for good or runs out of retries
+fn with_retries<T>(options: &ScanOptions, file_path: &Path, mut operation: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    let mut delay = RETRY_DELAY;
+    let mut attempt = 0;
+    loop {
+        match operation() {
+            Err(e) if attempt < options.retries && is_transient(&e) => {
+                attempt += 1;
+                if options.verbosity >= 1 {
+                    eprintln!("{} Retrying {} ({} of {}) in {} ms: {}",
+                        "🔁".dimmed(),
+                        file_path.display(),
+                        attempt,
+                        options.retries,
+                        delay.as_millis(),
+                        e.to_string().dimmed());
+                }
+                thread::sleep(delay);
+                delay *= 2;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn open_log_file(file_path: &Path, options: &ScanOptions) -> Result<fs::File> {
+    if !file_path.exists() {
+        return Err(AppError::IoError(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("File {:?} does not exist", file_path)
+        )));
+    }
+
+    with_retries(options, file_path, || fs::File::open(file_path)).map_err(|e| match e.kind() {
+        io::ErrorKind::PermissionDenied =>
+            AppError::PermissionDenied(format!("Access denied to file {:?}", file_path)),
+        io::ErrorKind::InvalidData =>
+            AppError::EncodingError(format!("Invalid file encoding in {:?}", file_path)),
+        _ => AppError::FileProcessingError {
+            path: file_path.to_path_buf(),
+            error: e.to_string(),
+        },
+    })
+}
+
+fn scan_open_file(file: fs::File, file_path: &Path, options: &ScanOptions) -> Result<FileReport> {
+    let metadata = with_retries(options, file_path, || file.metadata()).map_err(|e| AppError::FileProcessingError {
+        path: file_path.to_path_buf(),
+        error: format!("Failed to read file metadata: {}", e),
+    })?;
+
+    validate_file_size(metadata.len(), options.max_size, file_path)?;
+
+    let file_size = metadata.len();
+    let is_large_file = file_size > 100_000_000;
+
+    if is_large_file {
+        eprintln!("{} {} ({}) - Processing may take time...",
+            "📦".warn(),
+            "Large file detected".warn().bold(),
+            format_size(file_size, BINARY).warn());
+    }
+
+    // Entries without a parseable timestamp fall back to the file's mtime
+    let mut collector = EntryCollector::new(options, metadata.modified().ok());
+    let compressed = is_gzip_file(file_path);
+
+    // An incremental scan reads from where the last one stopped up to the
+    // last complete line. Compressed and UTF-16 files can't be entered
+    // midway, so they are rescanned in full, but only when they change.
+    let resumable = options.incremental.is_some() && !compressed && !is_utf16(&file, options);
+    let (start, lines_before) = match &options.incremental {
+        Some(state) if resumable => state.resume_point(file_path, &metadata),
+        Some(state) if state.is_unchanged(file_path, &metadata) => return Ok(collector.finish()),
+        _ => (0, 0),
+    };
+    let end = if resumable {
+        last_line_end(&file, start, file_size).map_err(|e| AppError::FileProcessingError {
+            path: file_path.to_path_buf(),
+            error: e.to_string(),
+        })?
+    } else {
+        file_size
+    };
+
+    let mut mapped_lines = None;
+    if options.use_mmap && file_size >= MMAP_THRESHOLD && !compressed && options.encoding.is_none() {
+        // Safety: the mapping is only read, and only while this function
+        // runs. A file truncated by another process in the meantime can
+        // still bring the process down with SIGBUS, which is why mapping is
+        // opt-in. Falls back to buffered reading if mapping fails.
+        if let Ok(mmap) = unsafe { Mmap::map(&file) } {
+            // UTF-16 files need transcoding, which the buffered path handles,
+            // and so does a file truncated to before `start` since it was
+            // checked
+            let truncated = start > mmap.len() as u64;
+            if !truncated && !mmap.starts_with(&[0xFF, 0xFE]) && !mmap.starts_with(&[0xFE, 0xFF]) {
+                let bytes = &mmap[start as usize..(end as usize).min(mmap.len())];
+                mapped_lines = Some(scan_mapped(bytes, lines_before, file_path, options, &mut collector)?);
+            }
+        }
+    }
+
+    let lines_read = match mapped_lines {
+        Some(lines) => lines,
+        None => scan_buffered(file, start..end, lines_before, file_path, options, &mut collector)?,
+    };
+
+    if let Some(state) = &options.incremental {
+        state.record(file_path, &metadata, end, lines_before + lines_read);
+    }
+    let mut report = collector.finish();
+    report.changed_during_scan = changed_since(file_path, &metadata);
+    report.bytes_read = end - start;
+    report.lines_read = lines_read;
+    Ok(report)
+}
+
+// Whether the file at `file_path` no longer has the size and modification
+// time it had when scanning started. A file that was rotated away counts as
+// changed too.
+fn changed_since(file_path: &Path, metadata: &fs::Metadata) -> bool {
+    fs::metadata(file_path).map_or(true, |current| {
+        current.len() != metadata.len() || current.modified().ok() != metadata.modified().ok()
+    })
+}
+
+// Whether the file needs transcoding from UTF-16, going by --encoding or its
+// byte order mark
+fn is_utf16(mut file: &fs::File, options: &ScanOptions) -> bool {
+    if options.encoding.is_some_and(|encoding| encoding == UTF_16LE || encoding == UTF_16BE) {
+        return true;
+    }
+    let mut bom = [0; 2];
+    let is_utf16 = file.read_exact(&mut bom).is_ok() && (bom == [0xFF, 0xFE] || bom == [0xFE, 0xFF]);
+    let _ = file.rewind();
+    is_utf16
+}
+
+// Finds the offset just past the last newline between `start` and `end`, or
+// `start` if there is none, so a line still being written isn't split
+fn last_line_end(mut file: &fs::File, start: u64, end: u64) -> io::Result<u64> {
+    let mut chunk = vec![0; 64 * 1024];
+    let mut chunk_end = end;
+    while chunk_end > start {
+        let chunk_start = chunk_end.saturating_sub(chunk.len() as u64).max(start);
+        let buffer = &mut chunk[..(chunk_end - chunk_start) as usize];
+        file.seek(SeekFrom::Start(chunk_start))?;
+        file.read_exact(buffer)?;
+        if let Some(newline) = memchr::memrchr(b'\n', buffer) {
+            return Ok(chunk_start + newline as u64 + 1);
+        }
+        chunk_end = chunk_start;
+    }
+    Ok(start)
+}
+
+// The error read_line_bounded fails with when told to stop. It is its own
+// type so a real TimedOut from a network filesystem isn't taken for it.
+#[derive(Debug)]
+struct ReadStopped;
+
+impl fmt::Display for ReadStopped {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "reading was stopped")
+    }
+}
+
+impl std::error::Error for ReadStopped {}
+
+pub(crate) fn is_read_stopped(error: &io::Error) -> bool {
+    error.get_ref().is_some_and(|inner| inner.is::<ReadStopped>())
+}
+
+// Reads one line into `buffer` without its line ending, keeping at most
+// `max_len` bytes and skipping past the rest, so a file without newlines
+// can't exhaust memory. Returns whether the line was cut short, or `None` at
+// the end of the input. Fails with `ReadStopped` (see `is_read_stopped`) once
+// `stop` says so, even in the middle of a line.
+pub(crate) fn read_line_bounded(
+    reader: &mut impl BufRead,
+    buffer: &mut Vec<u8>,
+    max_len: usize,
+    stop: impl Fn() -> bool,
+) -> io::Result<Option<bool>> {
+    let mut read_any = false;
+    let mut truncated = false;
+
+    loop {
+        if stop() {
+            return Err(io::Error::other(ReadStopped));
+        }
+        let available = match reader.fill_buf() {
+            Ok(available) => available,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if available.is_empty() {
+            // A last line without a newline ends the same way as any other
+            if buffer.last() == Some(&b'\r') {
+                buffer.pop();
+            }
+            return Ok(read_any.then_some(truncated));
+        }
+        read_any = true;
+
+        let (chunk, consumed, line_done) = match memchr::memchr(b'\n', available) {
+            // Strip CRLF line endings the same way BufRead::lines does
+            Some(0) if buffer.last() == Some(&b'\r') => {
+                buffer.pop();
+                (&available[..0], 1, true)
+            }
+            Some(end) => {
+                let line = &available[..end];
+                (line.strip_suffix(b"\r").unwrap_or(line), end + 1, true)
+            }
+            None => (available, available.len(), false),
+        };
+        let room = max_len.saturating_sub(buffer.len());
+        truncated |= chunk.len() > room;
+        buffer.extend_from_slice(&chunk[..chunk.len().min(room)]);
+        reader.consume(consumed);
+
+        if line_done {
+            return Ok(Some(truncated));
+        }
+    }
+}
+
+// Reads `span` of the file through a buffer, decompressing and transcoding as
+// needed, and returns the number of lines read. Compressed files are always
+// read whole.
+fn scan_buffered(
+    mut file: fs::File,
+    span: Range<u64>,
+    lines_before: usize,
+    file_path: &Path,
+    options: &ScanOptions,
+    collector: &mut EntryCollector,
+) -> Result<usize> {
+    let compressed = is_gzip_file(file_path);
+
+    // Rotated logs are often gzipped; decompress them transparently so line
+    // numbers refer to the logical line within the decompressed stream
+    let source: Box<dyn Read> = if compressed {
+        Box::new(GzDecoder::new(file))
+    } else {
+        file.seek(SeekFrom::Start(span.start))?;
+        Box::new(file.take(span.end - span.start))
+    };
+    scan_stream(source, span.end - span.start, compressed, lines_before, file_path, options, collector)
+}
+
+/// Scans one member of an archive, read from `source`, the way
+/// [`process_log_file`] scans a file. Returns `None` if the member doesn't
+/// look like text.
+pub(crate) fn process_member(
+    source: impl Read,
+    name: &str,
+    size: u64,
+    modified: Option<SystemTime>,
+    member_path: &Path,
+    options: &ScanOptions,
+) -> Result<Option<FileReport>> {
+    validate_file_size(size, options.max_size, member_path)?;
+    let compressed = is_gzip_file(Path::new(name));
+    let mut source: Box<dyn Read + '_> = if compressed {
+        Box::new(GzDecoder::new(source))
+    } else {
+        Box::new(source)
+    };
+
+    // The start of the member is sniffed like the start of a file on disk,
+    // then put back in front of the rest
+    let mut head = Vec::new();
+    source.by_ref().take(options.sniff.bytes as u64).read_to_end(&mut head).map_err(|e| AppError::FileProcessingError {
+        path: member_path.to_path_buf(),
+        error: e.to_string(),
+    })?;
+    if !options.all_files && !has_known_extension(Path::new(name), &options.extra_extensions) && !options.sniff.looks_like_text(&head[..]) {
+        return Ok(None);
+    }
+
+    let mut collector = EntryCollector::new(options, modified);
+    // Like a compressed file, a member is read through to its end and a
+    // failed read ends it
+    let lines_read = scan_stream(head.chain(source), size, true, 0, member_path, options, &mut collector)?;
+    let mut report = collector.finish();
+    report.bytes_read = size;
+    report.lines_read = lines_read;
+    Ok(Some(report))
+}
+
+// Reads lines from `source`, `len` bytes long before any decompression,
+// into the collector and returns how many were read. Compressed sources have
+// their decompressed size checked as they go and are given up on at the
+// first read error.
+fn scan_stream(
+    source: impl Read,
+    len: u64,
+    compressed: bool,
+    lines_before: usize,
+    file_path: &Path,
+    options: &ScanOptions,
+    collector: &mut EntryCollector,
+) -> Result<usize> {
+    // UTF-16 files with a byte order mark are transcoded to UTF-8 before
+    // matching; anything else is decoded with --encoding if one was given
+    let decoded = DecodeReaderBytesBuilder::new()
+        .encoding(options.encoding)
+        .bom_override(true)
+        .build(source);
+
+    let mut reader = io::BufReader::with_capacity(options.read_buffer_size(len), decoded);
+    let mut decompressed_size: u64 = 0;
+    let mut lines_read = 0;
+    let mut line = Vec::new();
+    let start_time = Instant::now();
+    let timed_out = || options.timeout.is_some_and(|timeout| start_time.elapsed() > timeout);
+
+    loop {
+        check_timeout(start_time, options, file_path)?;
+        line.clear();
+        let line_number = lines_before + lines_read + 1;
+
+        match read_line_bounded(&mut reader, &mut line, options.max_line_length, timed_out) {
+            Ok(None) => break,
+            Ok(Some(truncated)) => {
+                lines_read += 1;
+                if compressed {
+                    decompressed_size += line.len() as u64 + 1;
+                    validate_file_size(decompressed_size, options.max_size, file_path)?;
+                }
+                if !options.byte_patterns.is_empty() {
+                    collector.push_escaped_line(line_number, &line, truncated);
+                    continue;
+                }
+                match std::str::from_utf8(&line) {
+                    Ok(text) => collector.push_read_line(line_number, text, truncated),
+                    // The cut can land inside a multi-byte character
+                    Err(e) if truncated && e.error_len().is_none() => {
+                        let text = std::str::from_utf8(&line[..e.valid_up_to()]).unwrap_or_default();
+                        collector.push_read_line(line_number, text, truncated);
+                    }
+                    // Skip invalid UTF-8 lines, keeping context lines contiguous
+                    Err(_) => collector.break_context(),
+                }
+            }
+            Err(e) if is_read_stopped(&e) => check_timeout(start_time, options, file_path)?,
+            // A corrupt gzip stream keeps failing, so give up on the file
+            Err(e) if compressed => {
+                return Err(AppError::FileProcessingError {
+                    path: file_path.to_path_buf(),
+                    error: format!("Failed to decompress: {}", e),
+                });
+            },
+            Err(e) => {
+                lines_read += 1;
+                eprintln!("{} Line {} in {:?}: {}",
+                    "⚠️".warn(),
+                    line_number,
+                    file_path,
+                    e.to_string().bad());
+            }
+        }
+    }
+
+    Ok(lines_read)
+}
+
+// Scans a memory-mapped file in place, so only lines that end up in the
+// report are copied into Strings. Returns the number of lines read.
+fn scan_mapped(bytes: &[u8], lines_before: usize, file_path: &Path, options: &ScanOptions, collector: &mut EntryCollector) -> Result<usize> {
+    let mut remaining = bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes);
+    let mut line_number = lines_before;
+    let start_time = Instant::now();
+
+    while !remaining.is_empty() {
+        check_timeout(start_time, options, file_path)?;
+
+        let (line, rest) = match memchr::memchr(b'\n', remaining) {
+            Some(end) => (&remaining[..end], &remaining[end + 1..]),
+            None => (remaining, &[][..]),
+        };
+        remaining = rest;
+        line_number += 1;
+
+        // Strip CRLF line endings the same way BufRead::lines does
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if !options.byte_patterns.is_empty() {
+            collector.push_raw_line(line_number, line);
+            continue;
+        }
+        match std::str::from_utf8(line) {
+            Ok(line) => collector.push_line(line_number, line),
+            Err(_) => collector.break_context(),
+        }
+    }
+
+    Ok(line_number - lines_before)
+}
@@ -0,0 +1,145 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use colored::control::{self, SHOULD_COLORIZE};
+use serde::Serialize;
+use rustwatch::{AppError, FileReport, Result};
+
+use crate::{print_file_errors, ContentStyle, JsonFileReport, MatchLabels, OutputFormat};
+
+// Leaves room for the extension within the usual 255-byte limit on names
+const MAX_NAME_LEN: usize = 200;
+// No report name starts with an underscore, so the index can't clash with one
+const INDEX_NAME: &str = "_index";
+
+/// The reports written for a `--split-output` directory.
+pub struct SplitReports {
+    pub written: usize,
+    pub index: PathBuf,
+}
+
+#[derive(Serialize)]
+struct IndexEntry<'a> {
+    path: &'a str,
+    report: String,
+    match_count: usize,
+}
+
+/// Turns a display path into a single file name that stays inside the
+/// output directory.
+///
+/// Separators, colons (as in archive members) and `%` itself are
+/// percent-escaped, so different paths never share a name and `..` can't
+/// climb out. So are a leading `.` or `_`, keeping reports visible and apart
+/// from the index. Very long names keep their end, behind a hash of the whole.
+pub fn report_file_name(path: &str) -> String {
+    let mut name = String::with_capacity(path.len());
+    for (i, c) in path.chars().enumerate() {
+        match c {
+            '%' => name.push_str("%25"),
+            '/' => name.push_str("%2F"),
+            '\\' => name.push_str("%5C"),
+            ':' => name.push_str("%3A"),
+            '.' if i == 0 => name.push_str("%2E"),
+            '_' if i == 0 => name.push_str("%5F"),
+            c if c.is_control() => name.push_str(&format!("%{:02X}", u32::from(c))),
+            c => name.push(c),
+        }
+    }
+    if name.len() <= MAX_NAME_LEN {
+        return name;
+    }
+
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    let mut start = name.len() - (MAX_NAME_LEN - 17);
+    while !name.is_char_boundary(start) {
+        start += 1;
+    }
+    format!("{:016x}-{}", hasher.finish(), &name[start..])
+}
+
+/// Writes each file's matches to a report of its own in `dir`, as text or
+/// JSON, along with an index listing them.
+pub fn write_split_reports(
+    dir: &Path,
+    errors_by_file: &[(String, FileReport)],
+    format: OutputFormat,
+    style: &ContentStyle,
+    labels: MatchLabels,
+) -> Result<SplitReports> {
+    // Reports are files, so they are written without colors
+    let colorize = SHOULD_COLORIZE.should_colorize();
+    control::set_override(false);
+    let result = write_all(dir, errors_by_file, format, style, labels);
+    control::set_override(colorize);
+    result
+}
+
+fn output_error(path: &Path, e: io::Error) -> AppError {
+    AppError::InvalidInput(format!("Cannot write report {}: {}", path.display(), e))
+}
+
+fn write_all(
+    dir: &Path,
+    errors_by_file: &[(String, FileReport)],
+    format: OutputFormat,
+    style: &ContentStyle,
+    labels: MatchLabels,
+) -> Result<SplitReports> {
+    fs::create_dir_all(dir).map_err(|e| output_error(dir, e))?;
+    let extension = if format == OutputFormat::Json { "json" } else { "txt" };
+
+    let mut index = Vec::new();
+    for (file_name, report) in errors_by_file {
+        let report_name = format!("{}.{}", report_file_name(file_name), extension);
+        let report_path = dir.join(&report_name);
+        write_report(&report_path, file_name, report, format, style, labels)
+            .map_err(|e| output_error(&report_path, e))?;
+        index.push(IndexEntry { path: file_name, report: report_name, match_count: report.match_count });
+    }
+
+    let index_path = dir.join(format!("{}.{}", INDEX_NAME, extension));
+    write_index(&index_path, &index, format).map_err(|e| output_error(&index_path, e))?;
+    Ok(SplitReports { written: index.len(), index: index_path })
+}
+
+fn write_report(
+    path: &Path,
+    file_name: &str,
+    report: &FileReport,
+    format: OutputFormat,
+    style: &ContentStyle,
+    labels: MatchLabels,
+) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    if format == OutputFormat::Json {
+        serde_json::to_writer_pretty(&mut out, &JsonFileReport {
+            path: file_name,
+            match_count: report.match_count,
+            truncated: report.is_truncated(),
+            changed_during_scan: report.changed_during_scan,
+            errors: &report.entries,
+        })?;
+        writeln!(out)?;
+    } else {
+        print_file_errors(&mut out, file_name, report, style, labels)?;
+    }
+    out.flush()
+}
+
+// One line per report in the text index: match count, scanned file, report file
+fn write_index(path: &Path, index: &[IndexEntry], format: OutputFormat) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    if format == OutputFormat::Json {
+        serde_json::to_writer_pretty(&mut out, index)?;
+        writeln!(out)?;
+    } else {
+        for entry in index {
+            writeln!(out, "{}\t{}\t{}", entry.match_count, entry.path, entry.report)?;
+        }
+    }
+    out.flush()
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, Result};
+
+// How far the last incremental scan got through a file
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+struct FilePosition {
+    // Bytes scanned, always ending on a line boundary
+    offset: u64,
+    // Lines in those bytes, so numbering carries on from there
+    lines: usize,
+    // Size and modification time when the file was scanned
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct StateFile {
+    files: HashMap<PathBuf, FilePosition>,
+}
+
+/// Remembers how far each file has been scanned, so that an incremental scan
+/// (see [`ScanOptions::incremental`](crate::ScanOptions::incremental)) only
+/// reads what was appended since.
+///
+/// A file that shrank or whose modification time went backwards is assumed
+/// to have been rotated and is scanned from the start again.
+#[derive(Debug, Default)]
+pub struct ScanState {
+    positions: Mutex<HashMap<PathBuf, FilePosition>>,
+}
+
+// Files are remembered by their canonical path, so the same file reached
+// from a different scan root still resumes
+pub(crate) fn state_key(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+impl ScanState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads a state file written by [`ScanState::save`]. A missing file is
+    /// an empty state.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(e) => return Err(AppError::IoError(e)),
+        };
+        let state: StateFile = serde_json::from_str(&text).map_err(|e| AppError::InvalidInput(
+            format!("Invalid state file {}: {}", path.display(), e)
+        ))?;
+        Ok(Self { positions: Mutex::new(state.files) })
+    }
+
+    /// Writes the state to `path`, creating its directory if needed.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let state = StateFile { files: self.positions.lock().unwrap().clone() };
+        let json = serde_json::to_string(&state).map_err(io::Error::from)?;
+
+        // Write to a temporary file first so an interrupted save can't leave
+        // a truncated state behind
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, json)?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    fn position(&self, path: &Path) -> Option<FilePosition> {
+        self.positions.lock().unwrap().get(&state_key(path)).copied()
+    }
+
+    // The byte offset and line count to resume from, or the start of the
+    // file if it is new or looks rotated
+    pub(crate) fn resume_point(&self, path: &Path, metadata: &fs::Metadata) -> (u64, usize) {
+        let modified = metadata.modified().ok();
+        match self.position(path) {
+            Some(position) if metadata.len() >= position.offset && modified >= position.modified =>
+                (position.offset, position.lines),
+            _ => (0, 0),
+        }
+    }
+
+    // Whether the file is exactly as it was at the last scan
+    pub(crate) fn is_unchanged(&self, path: &Path, metadata: &fs::Metadata) -> bool {
+        self.position(path).is_some_and(|position| {
+            position.len == metadata.len() && position.modified == metadata.modified().ok()
+        })
+    }
+
+    pub(crate) fn record(&self, path: &Path, metadata: &fs::Metadata, offset: u64, lines: usize) {
+        let position = FilePosition {
+            offset,
+            lines,
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        };
+        self.positions.lock().unwrap().insert(state_key(path), position);
+    }
+}
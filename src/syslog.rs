@@ -0,0 +1,54 @@
+use crate::scanner::Severity;
+
+/// Facility names in the order of their syslog codes, so a name's index is
+/// its code.
+pub const FACILITY_NAMES: [&str; 24] = [
+    "kern", "user", "mail", "daemon", "auth", "syslog", "lpr", "news",
+    "uucp", "cron", "authpriv", "ftp", "ntp", "security", "console", "solaris-cron",
+    "local0", "local1", "local2", "local3", "local4", "local5", "local6", "local7",
+];
+
+/// The syslog code of a facility name such as `auth` or `local3`.
+pub fn facility_code(name: &str) -> Option<u8> {
+    FACILITY_NAMES.iter()
+        .position(|facility| facility.eq_ignore_ascii_case(name))
+        .and_then(|code| u8::try_from(code).ok())
+}
+
+// The facility and severity packed into a line's <PRI> prefix
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Priority {
+    pub(crate) facility: u8,
+    severity: u8,
+}
+
+impl Priority {
+    // Syslog's eight severities mapped onto the five levels RustWatch knows
+    pub(crate) fn level(self) -> Severity {
+        match self.severity {
+            0..=2 => Severity::Critical,
+            3 => Severity::Error,
+            4 => Severity::Warn,
+            5 | 6 => Severity::Info,
+            _ => Severity::Debug,
+        }
+    }
+}
+
+/// Splits the `<PRI>` prefix of an RFC 3164 or RFC 5424 line from the rest,
+/// which starts at the timestamp. Returns `None` for lines without one.
+pub(crate) fn split_priority(line: &str) -> Option<(Priority, &str)> {
+    let rest = line.strip_prefix('<')?;
+    let end = rest.find('>')?;
+    let digits = &rest[..end];
+    if digits.is_empty() || digits.len() > 3 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    // Facilities go up to local7 (23), so the highest priority is 23 * 8 + 7
+    let value: u8 = digits.parse().ok().filter(|value| *value <= 191)?;
+
+    // RFC 5424 puts a version number between the priority and the timestamp
+    let rest = &rest[end + 1..];
+    let rest = rest.strip_prefix("1 ").unwrap_or(rest);
+    Some((Priority { facility: value / 8, severity: value % 8 }, rest))
+}
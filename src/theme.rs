@@ -0,0 +1,146 @@
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+use colored::{Color, ColoredString, Colorize};
+
+/// The palette console output is drawn in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Theme {
+    /// Bright colors for dark terminal backgrounds
+    #[default]
+    Dark,
+    /// Darker colors that stay readable on light backgrounds
+    Light,
+    /// No colors, only bold and dim
+    Mono,
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "dark" => Ok(Theme::Dark),
+            "light" => Ok(Theme::Light),
+            "mono" => Ok(Theme::Mono),
+            _ => Err(format!("unknown theme '{}'", value)),
+        }
+    }
+}
+
+// Stored as the enum's discriminant, set once at startup
+static CURRENT: AtomicU8 = AtomicU8::new(Theme::Dark as u8);
+
+impl Theme {
+    /// Makes this the theme that [`Themed`] text is painted in.
+    pub fn apply(self) {
+        CURRENT.store(self as u8, Ordering::Relaxed);
+    }
+
+    /// The theme set with [`Theme::apply`], dark by default.
+    pub fn current() -> Self {
+        match CURRENT.load(Ordering::Relaxed) {
+            1 => Theme::Light,
+            2 => Theme::Mono,
+            _ => Theme::Dark,
+        }
+    }
+
+    fn paint(self, text: ColoredString, role: Role) -> ColoredString {
+        let color = match (self, role) {
+            (Theme::Mono, Role::Info | Role::Good) => return text,
+            (Theme::Mono, Role::Warn | Role::Bad | Role::Critical) => return text.bold(),
+            (Theme::Mono, Role::Accent | Role::Note) => return text.dimmed(),
+            (Theme::Dark, Role::Info) => Color::Cyan,
+            (Theme::Dark, Role::Good) => Color::Green,
+            (Theme::Dark, Role::Warn) => Color::Yellow,
+            (Theme::Dark, Role::Bad) => Color::Red,
+            (Theme::Dark, Role::Critical) => Color::BrightRed,
+            (Theme::Dark, Role::Accent) => Color::Blue,
+            (Theme::Dark, Role::Note) => Color::Magenta,
+            (Theme::Light, Role::Info) => Color::Blue,
+            (Theme::Light, Role::Good) => Color::Green,
+            (Theme::Light, Role::Warn) => Color::Magenta,
+            (Theme::Light, Role::Bad | Role::Critical) => Color::Red,
+            (Theme::Light, Role::Accent) => Color::Black,
+            (Theme::Light, Role::Note) => Color::Cyan,
+        };
+        text.color(color)
+    }
+}
+
+// What a piece of output is, which the theme turns into a color
+#[derive(Clone, Copy)]
+enum Role {
+    Info,
+    Good,
+    Warn,
+    Bad,
+    Critical,
+    Accent,
+    Note,
+}
+
+/// Paints text by what it means rather than in a fixed color, using the
+/// current [`Theme`].
+pub trait Themed: Sized {
+    fn into_colored(self) -> ColoredString;
+
+    /// Headings, labels and other neutral decoration
+    fn info(self) -> ColoredString {
+        Theme::current().paint(self.into_colored(), Role::Info)
+    }
+
+    /// Success and counts of things that went well
+    fn good(self) -> ColoredString {
+        Theme::current().paint(self.into_colored(), Role::Good)
+    }
+
+    /// Warnings and counts worth a look
+    fn warn(self) -> ColoredString {
+        Theme::current().paint(self.into_colored(), Role::Warn)
+    }
+
+    /// Errors and failures
+    fn bad(self) -> ColoredString {
+        Theme::current().paint(self.into_colored(), Role::Bad)
+    }
+
+    /// The most severe log levels
+    fn critical(self) -> ColoredString {
+        Theme::current().paint(self.into_colored(), Role::Critical)
+    }
+
+    /// Timestamps and list numbers
+    fn accent(self) -> ColoredString {
+        Theme::current().paint(self.into_colored(), Role::Accent)
+    }
+
+    /// Side notes such as repeat counts
+    fn note(self) -> ColoredString {
+        Theme::current().paint(self.into_colored(), Role::Note)
+    }
+}
+
+impl Themed for &str {
+    fn into_colored(self) -> ColoredString {
+        ColoredString::from(self)
+    }
+}
+
+impl Themed for &String {
+    fn into_colored(self) -> ColoredString {
+        ColoredString::from(self.as_str())
+    }
+}
+
+impl Themed for String {
+    fn into_colored(self) -> ColoredString {
+        ColoredString::from(self)
+    }
+}
+
+impl Themed for ColoredString {
+    fn into_colored(self) -> ColoredString {
+        self
+    }
+}
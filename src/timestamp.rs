@@ -0,0 +1,69 @@
+use std::time::SystemTime;
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// Formats a timestamp in local time the way it appears in reports.
+pub fn format_timestamp(timestamp: SystemTime) -> String {
+    let datetime: DateTime<Local> = timestamp.into();
+    datetime.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+pub(crate) fn serialize_timestamp<S: Serializer>(timestamp: &Option<SystemTime>, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    match timestamp {
+        Some(ts) => serializer.serialize_str(&format_timestamp(*ts)),
+        None => serializer.serialize_none(),
+    }
+}
+
+// Reads back what serialize_timestamp wrote, in local time
+pub(crate) fn deserialize_timestamp<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<SystemTime>, D::Error> {
+    let Some(text) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    let naive = NaiveDateTime::parse_from_str(&text, "%Y-%m-%d %H:%M:%S").map_err(serde::de::Error::custom)?;
+    Local.from_local_datetime(&naive)
+        .earliest()
+        .map(|datetime| Some(datetime.into()))
+        .ok_or_else(|| serde::de::Error::custom(format!("nonexistent local time '{}'", text)))
+}
+
+/// Extracts the timestamp at the start of a log line.
+///
+/// Understands ISO 8601 (with or without an offset), syslog's
+/// `Mon DD HH:MM:SS` and bracketed `[YYYY-MM-DD HH:MM:SS]` forms.
+pub fn parse_line_timestamp(line: &str) -> Option<SystemTime> {
+    // Log timestamps usually lead the line, sometimes wrapped in brackets
+    let line = line.trim_start();
+    let line = line.strip_prefix('[').unwrap_or(line);
+
+    parse_iso_timestamp(line)
+        .or_else(|| parse_syslog_timestamp(line))
+}
+
+fn parse_iso_timestamp(line: &str) -> Option<SystemTime> {
+    // Timestamps with an explicit offset, e.g. 2024-06-20T12:34:56.789+02:00
+    let token = line.split_whitespace().next()?.trim_end_matches([']', ',']);
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(token) {
+        return Some(datetime.into());
+    }
+
+    // Timestamps without an offset are taken to be local time
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_and_remainder(line, format).ok())
+        .and_then(|(naive, _)| Local.from_local_datetime(&naive).earliest())
+        .map(SystemTime::from)
+}
+
+fn parse_syslog_timestamp(line: &str) -> Option<SystemTime> {
+    // Syslog timestamps (e.g. "Jun  5 12:34:56") omit the year, so assume the
+    // current one unless that would put the entry in the future
+    let stamp = line.get(..15)?;
+    let now = Local::now();
+    [now.year(), now.year() - 1]
+        .iter()
+        .filter_map(|year| NaiveDateTime::parse_from_str(&format!("{} {}", year, stamp), "%Y %b %e %H:%M:%S").ok())
+        .filter_map(|naive| Local.from_local_datetime(&naive).earliest())
+        .find(|datetime| *datetime <= now + chrono::Duration::days(1))
+        .map(SystemTime::from)
+}
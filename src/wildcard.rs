@@ -0,0 +1,98 @@
+use std::ops::Range;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+use crate::error::{AppError, Result};
+
+/// Keywords with shell-style wildcards, matched against each word of a line:
+/// `*` stands for any run of characters and `?` for a single one, so `err*`
+/// matches "error" and "errno", and `*timeout*` any word containing
+/// "timeout".
+///
+/// Words are the runs of non-whitespace in a line, tried both as they are
+/// and without leading and trailing punctuation, so that `error` matches
+/// "[error]" and "error:". The patterns are compiled once and can be shared
+/// between threads.
+#[derive(Clone, Debug)]
+pub struct WildcardKeywords {
+    patterns: Vec<String>,
+    set: GlobSet,
+}
+
+impl WildcardKeywords {
+    pub fn new(patterns: &[String], case_sensitive: bool) -> Result<Self> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            let glob = GlobBuilder::new(&escape_brackets(pattern))
+                .case_insensitive(!case_sensitive)
+                .backslash_escape(true)
+                .build()
+                .map_err(|e| AppError::InvalidInput(format!("Invalid wildcard keyword '{}': {}", pattern, e)))?;
+            builder.add(glob);
+        }
+        let set = builder.build()
+            .map_err(|e| AppError::InvalidInput(format!("Invalid wildcard keywords: {}", e)))?;
+        Ok(Self { patterns: patterns.to_vec(), set })
+    }
+
+    /// Whether any word of `line` matches one of the patterns.
+    pub fn is_match(&self, line: &str) -> bool {
+        words(line).any(|word| self.set.is_match(&line[word.clone()]) || self.set.is_match(&line[trim_word(line, word)]))
+    }
+
+    /// The first pattern that a word of `line` matches.
+    pub fn matching_pattern(&self, line: &str) -> Option<&str> {
+        words(line)
+            .find_map(|word| {
+                self.set.matches(&line[word.clone()]).first().copied()
+                    .or_else(|| self.set.matches(&line[trim_word(line, word)]).first().copied())
+            })
+            .map(|index| self.patterns[index].as_str())
+    }
+
+    /// Byte ranges of the matching words in `line`, for highlighting.
+    pub fn word_ranges(&self, line: &str) -> Vec<Range<usize>> {
+        words(line)
+            .filter_map(|word| {
+                let trimmed = trim_word(line, word.clone());
+                if self.set.is_match(&line[trimmed.clone()]) {
+                    Some(trimmed)
+                } else {
+                    self.set.is_match(&line[word.clone()]).then_some(word)
+                }
+            })
+            .collect()
+    }
+}
+
+// Only * and ? are wildcards; brackets and braces are taken literally
+fn escape_brackets(pattern: &str) -> String {
+    let mut escaped = String::with_capacity(pattern.len());
+    for c in pattern.chars() {
+        if matches!(c, '[' | ']' | '{' | '}' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// Byte ranges of the runs of non-whitespace in a line
+fn words(line: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    line.split(char::is_whitespace)
+        .filter(|word| !word.is_empty())
+        .map(move |word| {
+            let start = word.as_ptr() as usize - line.as_ptr() as usize;
+            start..start + word.len()
+        })
+}
+
+// The word without punctuation around it
+fn trim_word(line: &str, word: Range<usize>) -> Range<usize> {
+    let text = &line[word.clone()];
+    let trimmed = text.trim_matches(|c: char| !c.is_alphanumeric());
+    if trimmed.is_empty() {
+        return word;
+    }
+    let start = word.start + (trimmed.as_ptr() as usize - text.as_ptr() as usize);
+    start..start + trimmed.len()
+}
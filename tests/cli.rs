@@ -0,0 +1,381 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+fn run_scan(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_rustwatch"))
+        .arg("--dir")
+        .arg(dir)
+        .arg("--yes")
+        .args(args)
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("failed to run rustwatch");
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn matched_lines(output: &str) -> Vec<usize> {
+    output
+        .lines()
+        .filter_map(|line| line.trim_start().strip_prefix("└─ Line "))
+        .filter_map(|rest| rest.split_whitespace().next())
+        .filter_map(|number| number.parse().ok())
+        .collect()
+}
+
+#[test]
+fn word_mode_matches_keyword_next_to_punctuation() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("app.log"),
+        "error.\n(error)\nerror: at start\nfailed with error\n",
+    )
+    .unwrap();
+
+    let output = run_scan(dir.path(), &["--word"]);
+    assert_eq!(matched_lines(&output), vec![1, 2, 3, 4]);
+}
+
+#[test]
+fn word_mode_rejects_keyword_inside_other_tokens() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("app.log"),
+        "preflight-error\nerrors\nterrorism\nerrorless\nno-error\n",
+    )
+    .unwrap();
+
+    let output = run_scan(dir.path(), &["--word"]);
+    assert!(matched_lines(&output).is_empty());
+}
+
+#[test]
+fn substring_mode_is_still_the_default() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), "preflight-error\nerrors\nall good\n").unwrap();
+
+    let output = run_scan(dir.path(), &[]);
+    assert_eq!(matched_lines(&output), vec![1, 2]);
+}
+
+#[test]
+fn case_sensitive_mode_excludes_other_cases() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), "Error: disk full\nerror: disk full\nERROR: disk full\n").unwrap();
+
+    let output = run_scan(dir.path(), &["--case-sensitive", "--keyword", "error"]);
+    assert_eq!(matched_lines(&output), vec![2]);
+}
+
+#[test]
+fn case_sensitive_mode_matches_uppercase_keyword_exactly() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), "MAX_RETRIES exceeded\nmax_retries exceeded\n").unwrap();
+
+    let output = run_scan(dir.path(), &["-s", "--keyword", "MAX_RETRIES"]);
+    assert_eq!(matched_lines(&output), vec![1]);
+}
+
+#[test]
+fn search_is_case_insensitive_by_default() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), "Error: disk full\nerror: disk full\nERROR: disk full\n").unwrap();
+
+    let output = run_scan(dir.path(), &["--keyword", "error"]);
+    assert_eq!(matched_lines(&output), vec![1, 2, 3]);
+}
+
+#[test]
+fn exclude_line_drops_matches_containing_any_pattern() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("app.log"),
+        "error: disk full\nerror handling initialized\nretrying after io error\nerror: timeout\n",
+    )
+    .unwrap();
+
+    let output = run_scan(dir.path(), &["--exclude-line", "handling initialized", "--exclude-line", "retrying * error"]);
+    assert_eq!(matched_lines(&output), vec![1, 4]);
+}
+
+#[test]
+fn glob_keyword_matches_whole_words_against_wildcards() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("app.log"),
+        "[ERRNO] 5\nterror struck\nReadTimeoutException thrown\nall fine\nerr: bad [x]\n",
+    )
+    .unwrap();
+
+    let output = run_scan(dir.path(), &["--glob-keyword", "err*", "--glob-keyword", "*timeout*"]);
+    assert_eq!(matched_lines(&output), vec![1, 3, 5]);
+
+    let output = run_scan(dir.path(), &["--glob-keyword", "[x]"]);
+    assert_eq!(matched_lines(&output), vec![5]);
+}
+
+#[test]
+fn first_and_last_pick_matches_by_timestamp_across_files() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("a.log"), "2024-06-20 09:00:00 error a1\n2024-06-20 11:00:00 error a2\n").unwrap();
+    fs::write(dir.path().join("b.log"), "2024-06-20 10:00:00 error b1\n2024-06-20 12:00:00 error b2\n").unwrap();
+
+    let output = run_scan(dir.path(), &["--first", "2", "--last", "1"]);
+    let shown: Vec<&str> = output
+        .lines()
+        .filter_map(|line| line.trim_start().strip_prefix("└─ "))
+        .filter_map(|rest| rest.split_whitespace().nth(2))
+        .filter(|location| location.contains(".log:"))
+        .collect();
+    assert_eq!(shown, ["a.log:1", "b.log:1", "b.log:2"]);
+}
+
+#[test]
+fn merge_continuation_reports_a_stack_trace_as_one_match() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("app.log"),
+        "2024-06-20 10:00:00 ERROR request failed\n\
+         java.lang.IllegalStateException: error in handler\n\
+         \x20   at com.example.Handler.run(Handler.java:42)\n\
+         2024-06-20 10:00:01 INFO recovered\n\
+         \x20   at com.example.Other.error(Other.java:7)\n",
+    )
+    .unwrap();
+
+    let output = run_scan(dir.path(), &["--merge-continuation", "untimed"]);
+    assert_eq!(matched_lines(&output), vec![1, 5]);
+    assert!(output.contains("Handler.java:42"));
+
+    let output = run_scan(dir.path(), &["--merge-continuation", "indented"]);
+    assert_eq!(matched_lines(&output), vec![1, 2, 5]);
+}
+
+#[test]
+fn group_by_dir_sums_matches_per_directory_at_the_given_depth() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("auth").join("api")).unwrap();
+    fs::create_dir_all(dir.path().join("web")).unwrap();
+    fs::write(dir.path().join("auth").join("api").join("a.log"), "error\nerror\n").unwrap();
+    fs::write(dir.path().join("auth").join("b.log"), "error\n").unwrap();
+    fs::write(dir.path().join("web").join("c.log"), "error\n").unwrap();
+
+    // The directories are relative to the scan root whatever --path-style shows
+    for path_style in ["relative", "basename", "absolute"] {
+        let output = run_scan(dir.path(), &["--quiet", "--group-by-dir", "--dir-depth", "1", "--path-style", path_style]);
+        let rows: Vec<Vec<&str>> = output
+            .lines()
+            .skip_while(|line| !line.contains("by directory"))
+            .skip(1)
+            .take_while(|line| !line.is_empty())
+            .map(|line| line.split_whitespace().collect())
+            .collect();
+        assert_eq!(rows, [["└─", "3", "(", "75%)", "auth"], ["└─", "1", "(", "25%)", "web"]], "--path-style {}", path_style);
+    }
+}
+
+#[test]
+fn tiny_read_buffer_still_finds_every_line() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), "starting up\r\nerror: disk full\nall good\nretrying after error").unwrap();
+
+    for buffer_size in ["1", "3", "64K"] {
+        let output = run_scan(dir.path(), &["--buffer-size", buffer_size]);
+        assert_eq!(matched_lines(&output), vec![2, 4], "--buffer-size {}", buffer_size);
+    }
+}
+
+#[test]
+fn quiet_if_clean_prints_nothing_without_matches() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), "all good\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rustwatch"))
+        .arg("--dir")
+        .arg(dir.path())
+        .args(["--yes", "--quiet-if-clean"])
+        .output()
+        .expect("failed to run rustwatch");
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(output.stderr.is_empty(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    fs::write(dir.path().join("app.log"), "all good\nerror: disk full\n").unwrap();
+    let output = run_scan(dir.path(), &["--quiet-if-clean"]);
+    assert_eq!(matched_lines(&output), vec![2]);
+    assert!(output.contains("Scan Statistics"));
+}
+
+#[test]
+fn byte_pattern_matches_raw_bytes_and_escapes_them() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("proto.log"),
+        b"frame ok \x01\x02\nframe bad \xde\xad\xbe\xef\x00 tail\nliteral \\xDE\\xAD\\xBE\\xEF text\n",
+    )
+    .unwrap();
+
+    let output = run_scan(dir.path(), &["--byte-pattern", "0xDEADBEEF"]);
+    assert_eq!(matched_lines(&output), vec![2]);
+    assert!(output.contains(r"frame bad \xDE\xAD\xBE\xEF\x00 tail"));
+
+    // "x00" as text must not match inside the escape of a NUL byte
+    let output = run_scan(dir.path(), &["--byte-pattern", "783030"]);
+    assert!(matched_lines(&output).is_empty());
+}
+
+#[test]
+fn split_output_writes_one_report_per_file_inside_the_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let logs = dir.path().join("logs");
+    fs::create_dir_all(logs.join("app")).unwrap();
+    fs::write(logs.join("app").join("server.log"), "error: disk full\n").unwrap();
+    fs::write(logs.join("..log"), "error: timeout\n").unwrap();
+    fs::write(logs.join("clean.log"), "all good\n").unwrap();
+    let reports = dir.path().join("reports");
+
+    run_scan(&logs, &["--split-output", reports.to_str().unwrap()]);
+
+    let mut names: Vec<String> = fs::read_dir(&reports)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["%2E.log.txt", "_index.txt", "app%2Fserver.log.txt"]);
+    let report = fs::read_to_string(reports.join("app%2Fserver.log.txt")).unwrap();
+    assert_eq!(matched_lines(&report), vec![1]);
+}
+
+#[test]
+fn progress_messages_stay_off_stdout() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("app.log"), "error: disk full\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rustwatch"))
+        .arg("--dir")
+        .arg(dir.path())
+        .arg("--yes")
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("failed to run rustwatch");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(stderr.contains("Starting scan"));
+    assert!(!stdout.contains("Starting scan"));
+    assert!(!stdout.contains("Files to be scanned"));
+    assert_eq!(matched_lines(&stdout), vec![1]);
+    assert!(stdout.contains("Scan Statistics"));
+}
+
+#[test]
+fn stdin_stream_matches_piped_lines() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rustwatch"))
+        .args(["--stdin-stream", "--format", "ndjson"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to run rustwatch");
+    child.stdin.take().unwrap().write_all(b"starting\nerror: disk full\nall good\nERROR: timeout\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    let lines: Vec<u64> = stdout
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["line_number"].as_u64().unwrap())
+        .collect();
+    assert_eq!(lines, [2, 4]);
+}
+
+#[test]
+fn stdin_stream_cuts_long_lines_at_max_line_length() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rustwatch"))
+        .args(["--stdin-stream", "--format", "ndjson", "--max-line-length", "1K"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to run rustwatch");
+    let mut input = b"error: ".to_vec();
+    input.extend(std::iter::repeat_n(b'x', 1024 * 1024));
+    input.extend_from_slice(b"\nerror: short\n");
+    child.stdin.take().unwrap().write_all(&input).unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    let entries: Vec<serde_json::Value> = stdout.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[1]["line_number"], 2);
+    assert!(entries[0]["content"].as_str().unwrap().len() < 2 * 1024);
+}
+
+#[test]
+fn max_files_counts_only_files_that_pass_the_filters() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["a.log", "b.log", "c.log"] {
+        fs::write(dir.path().join(name), "").unwrap();
+    }
+    fs::write(dir.path().join("d.log"), "error in d\n").unwrap();
+    fs::write(dir.path().join("e.log"), "error in e\n").unwrap();
+
+    let output = run_scan(dir.path(), &["--min-size", "1", "--max-files", "2"]);
+    assert!(output.contains("d.log"), "{}", output);
+    assert!(output.contains("e.log"), "{}", output);
+}
+
+#[test]
+fn max_files_does_not_count_a_file_reached_from_two_roots() {
+    let dir = tempfile::tempdir().unwrap();
+    let logs = dir.path().join("logs");
+    let nested = logs.join("nested");
+    let other = dir.path().join("other");
+    fs::create_dir_all(&nested).unwrap();
+    fs::create_dir(&other).unwrap();
+    fs::write(logs.join("top.log"), "error in top\n").unwrap();
+    fs::write(nested.join("nested.log"), "error in nested\n").unwrap();
+    fs::write(other.join("other.log"), "error in other\n").unwrap();
+
+    // nested is walked a second time as a root of its own
+    let output = Command::new(env!("CARGO_BIN_EXE_rustwatch"))
+        .arg(&logs)
+        .arg(&nested)
+        .arg(&other)
+        .args(["--yes", "--max-files", "3"])
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("failed to run rustwatch");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("other.log"), "{}", stdout);
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("--max-files limit"));
+}
+
+#[test]
+fn dry_run_writes_to_the_output_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let logs = dir.path().join("logs");
+    fs::create_dir(&logs).unwrap();
+    fs::write(logs.join("app.log"), "error: disk full\n").unwrap();
+    let report = dir.path().join("report.txt");
+
+    let stdout = run_scan(&logs, &["--dry-run", "--output", report.to_str().unwrap()]);
+    assert!(!stdout.contains("app.log"), "{}", stdout);
+    let written = fs::read_to_string(&report).unwrap();
+    assert!(written.contains("app.log") && written.contains("dry run"), "{}", written);
+}
+
+#[test]
+fn count_lists_files_with_equal_counts_in_natural_order() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["app10.log", "app2.log", "app1.log"] {
+        fs::write(dir.path().join(name), "error: disk full\n").unwrap();
+    }
+
+    let output = run_scan(dir.path(), &["--count"]);
+    let files: Vec<&str> = output
+        .lines()
+        .filter_map(|line| line.trim_start().strip_prefix("└─ 1 "))
+        .collect();
+    assert_eq!(files, ["app1.log", "app2.log", "app10.log"]);
+}
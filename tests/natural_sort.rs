@@ -0,0 +1,42 @@
+use std::cmp::Ordering;
+use std::fs;
+use rustwatch::{collect_files, natural_cmp, ScanOptions};
+
+#[test]
+fn numeric_suffixes_compare_by_value() {
+    assert_eq!(natural_cmp("app.log.2", "app.log.10"), Ordering::Less);
+    assert_eq!(natural_cmp("app.log.10", "app.log.2"), Ordering::Greater);
+    assert_eq!(natural_cmp("app.log.10", "app.log.10"), Ordering::Equal);
+}
+
+#[test]
+fn prefix_sorts_before_longer_name() {
+    assert_eq!(natural_cmp("app.log", "app.log.1"), Ordering::Less);
+}
+
+#[test]
+fn mixed_segments_compare_piecewise() {
+    let mut names = vec!["node10-db2.log", "node2-db10.log", "node2-db2.log", "node10-db1.log"];
+    names.sort_by(|a, b| natural_cmp(a, b));
+    assert_eq!(names, ["node2-db2.log", "node2-db10.log", "node10-db1.log", "node10-db2.log"]);
+}
+
+#[test]
+fn leading_zeros_do_not_change_value() {
+    assert_eq!(natural_cmp("app.log.002", "app.log.10"), Ordering::Less);
+    assert_ne!(natural_cmp("app.log.01", "app.log.1"), Ordering::Equal);
+}
+
+#[test]
+fn collected_files_use_natural_order() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["app.log.10", "app.log.2", "app.log.1", "app.log"] {
+        fs::write(dir.path().join(name), "error\n").unwrap();
+    }
+
+    let collected = collect_files(dir.path(), &ScanOptions::default()).unwrap();
+    let names: Vec<_> = collected.files.iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(names, ["app.log", "app.log.1", "app.log.2", "app.log.10"]);
+}
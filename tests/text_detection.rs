@@ -0,0 +1,103 @@
+use std::fs;
+use std::path::PathBuf;
+use rustwatch::{is_text_file, is_text_file_sniffing, SniffSettings};
+use tempfile::TempDir;
+
+const PNG_HEADER: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x10\0\0\0\x10\x08\x06\0\0\0\x1f\xf3\xffa";
+
+fn fixture(dir: &TempDir, name: &str, content: &[u8]) -> PathBuf {
+    let path = dir.path().join(name);
+    fs::write(&path, content).unwrap();
+    path
+}
+
+#[test]
+fn pure_ascii_is_text() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = fixture(&dir, "ascii", b"2024-06-20 12:00:00 ERROR connection refused\n");
+    assert!(is_text_file(&path));
+}
+
+#[test]
+fn utf8_with_accents_is_text() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = fixture(&dir, "accents", "Überprüfung fehlgeschlagen: Datei wurde nicht gefunden\n".as_bytes());
+    assert!(is_text_file(&path));
+}
+
+#[test]
+fn png_header_is_binary() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = fixture(&dir, "image", PNG_HEADER);
+    assert!(!is_text_file(&path));
+}
+
+#[test]
+fn null_bytes_are_binary() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = fixture(&dir, "zeros", &[0; 1024]);
+    assert!(!is_text_file(&path));
+}
+
+#[test]
+fn empty_file_is_not_text() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = fixture(&dir, "empty", b"");
+    assert!(!is_text_file(&path));
+}
+
+#[test]
+fn log_extension_skips_content_sniffing() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = fixture(&dir, "app.log", PNG_HEADER);
+    assert!(is_text_file(&path));
+}
+
+#[test]
+fn png_extension_falls_back_to_content() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = fixture(&dir, "image.png", PNG_HEADER);
+    let text = fixture(&dir, "notes.png", b"actually plain text\n");
+    assert!(!is_text_file(&binary));
+    assert!(is_text_file(&text));
+}
+
+#[test]
+fn no_extension_falls_back_to_content() {
+    let dir = tempfile::tempdir().unwrap();
+    let text = fixture(&dir, "messages", b"kernel: error reading block\n");
+    let binary = fixture(&dir, "core", &[0x7f, b'E', b'L', b'F', 2, 1, 1, 0, 0, 0, 0, 0]);
+    assert!(is_text_file(&text));
+    assert!(!is_text_file(&binary));
+}
+
+#[test]
+fn raising_non_ascii_ratio_accepts_cyrillic_text() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = fixture(&dir, "journal", "Ошибка: не удалось открыть файл журнала\n".as_bytes());
+    let lenient = SniffSettings { max_non_ascii_ratio: 1.0, ..SniffSettings::default() };
+    assert!(!is_text_file(&path));
+    assert!(is_text_file_sniffing(&path, &lenient));
+}
+
+#[test]
+fn raising_null_ratio_accepts_padded_text() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut content = b"service started, waiting for requests\n".repeat(4);
+    content.extend_from_slice(&[0; 8]);
+    let path = fixture(&dir, "padded", &content);
+    let lenient = SniffSettings { max_null_ratio: 0.1, ..SniffSettings::default() };
+    assert!(!is_text_file(&path));
+    assert!(is_text_file_sniffing(&path, &lenient));
+}
+
+#[test]
+fn sniff_bytes_limits_how_far_content_is_checked() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut content = b"plain header line\n".repeat(8);
+    content.extend_from_slice(&[0; 512]);
+    let path = fixture(&dir, "trailer", &content);
+    let short = SniffSettings { bytes: 64, ..SniffSettings::default() };
+    assert!(!is_text_file(&path));
+    assert!(is_text_file_sniffing(&path, &short));
+}
@@ -0,0 +1,37 @@
+use std::fs;
+use rustwatch::{process_log_file, ScanOptions};
+
+// Line number and content of each match in a file with the given content
+fn scan(content: &str) -> Vec<(usize, String)> {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app.log");
+    fs::write(&path, content).unwrap();
+
+    let report = process_log_file(&path, &ScanOptions::new(vec!["error".to_string()])).unwrap();
+    assert_eq!(report.match_count, report.entries.len());
+    report.entries.into_iter().map(|entry| (entry.line_number, entry.content)).collect()
+}
+
+#[test]
+fn single_line_matches_with_and_without_trailing_newline() {
+    assert_eq!(scan("error"), vec![(1, "error".to_string())]);
+    assert_eq!(scan("error\n"), scan("error"));
+}
+
+#[test]
+fn last_line_keeps_its_number_without_trailing_newline() {
+    assert_eq!(scan("ok\nfine\nerror"), vec![(3, "error".to_string())]);
+    assert_eq!(scan("ok\nfine\nerror\n"), scan("ok\nfine\nerror"));
+}
+
+#[test]
+fn crlf_last_line_matches_the_same_without_final_newline() {
+    assert_eq!(scan("ok\r\nerror\r\n"), vec![(2, "error".to_string())]);
+    assert_eq!(scan("ok\r\nerror\r"), scan("ok\r\nerror\r\n"));
+}
+
+#[test]
+fn trailing_newline_adds_no_extra_line() {
+    assert_eq!(scan("error\nerror\n").len(), 2);
+    assert!(scan("ok\n\n").is_empty());
+}